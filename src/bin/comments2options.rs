@@ -3,7 +3,7 @@ use clap::Parser;
 use miette::IntoDiagnostic;
 use protox::Compiler;
 use std::{io::Write, path::PathBuf};
-use protox_doc::comments2option::{comments2option, DescriptionIds};
+use protox_doc::comments2option::{comments2option, Config};
 
 #[derive(Debug, clap::Parser)]
 pub struct Args {
@@ -29,34 +29,8 @@ pub struct Args {
         value_parser
     )]
     output: PathBuf,
-    // options for DescriptionIds
-    /// The ID of the option for FileDescriptor comments
-    #[clap(long = "file-id", value_name = "ID")]
-    file: Option<u32>,
-    /// The ID of the option for Message comments
-    #[clap(long = "message-id", value_name = "ID")]
-    message: Option<u32>,
-    /// The ID of the option for Enum comments
-    #[clap(long = "enum-id", value_name = "ID")]
-    enum_: Option<u32>,
-    /// The ID of the option for Service comments
-    #[clap(long = "service-id", value_name = "ID")]
-    service: Option<u32>,
-    /// The ID of the option for Method comments
-    #[clap(long = "method-id", value_name = "ID")]
-    method: Option<u32>,
-    /// The ID of the option for Field comments
-    #[clap(long = "field-id", value_name = "ID")]
-    field: Option<u32>,
-    /// The ID of the option for EnumValue comments
-    #[clap(long = "enum-value-id", value_name = "ID")]
-    enum_value: Option<u32>,
-    /// The ID of the option for Extension comments
-    #[clap(long = "extension-id", value_name = "ID")]
-    extension: Option<u32>,
-    /// The ID of the option for Oneof comments
-    #[clap(long = "oneof-id", value_name = "ID")]
-    oneof: Option<u32>,    
+    #[clap(flatten)]
+    config: Config,
 }
 fn main() -> miette::Result<()> {
     miette::set_panic_hook();
@@ -64,17 +38,6 @@ fn main() -> miette::Result<()> {
 }
 fn entry_point(args: Args) -> miette::Result<()> {
     let mut compiler = Compiler::new(args.includes)?;
-    let ids = DescriptionIds {
-        file: args.file,
-        message: args.message,
-        enum_: args.enum_,
-        service: args.service,
-        method: args.method,
-        field: args.field,
-        enum_value: args.enum_value,
-        extension: args.extension,
-        oneof: args.oneof,
-    };
     compiler.include_imports(true);
     compiler.include_source_info(true);
     for file_glob in args.files {
@@ -86,7 +49,7 @@ fn entry_point(args: Args) -> miette::Result<()> {
         }
     }
     let res = compiler.encode_file_descriptor_set();
-    let res = comments2option(&res, &ids);
+    let res = comments2option(&res, &args.config)?;
     std::fs::File::create(&args.output).into_diagnostic()?.write_all(&res).into_diagnostic()?;
     Ok(())
 }
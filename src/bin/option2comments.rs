@@ -3,5 +3,5 @@ use protox_doc::option2comments::{entry_point, Args};
 
 fn main() -> miette::Result<()> {
     miette::set_panic_hook();
-    entry_point(Args::parse())
+    Ok(entry_point(Args::parse())?)
 }
@@ -0,0 +1,7 @@
+use clap::Parser;
+use protox_doc::render::{html::entry_point, Args};
+
+fn main() -> miette::Result<()> {
+    miette::set_panic_hook();
+    Ok(entry_point(Args::parse())?)
+}
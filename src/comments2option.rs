@@ -1,151 +1,575 @@
+use crate::error::{Error, Result};
+use crate::gen_doc_proto;
 use crate::path_resolver::protobuf::{PathedChilds, PathedDescriptor};
-use protobuf::{descriptor::FileDescriptorSet, Message};
+use crate::markdown;
+use crate::option2comments::describe;
+use crate::path_resolver::{prost::elements, tag};
+use crate::references::{qualify, Names};
+use crate::sidecar::{self, Sidecar};
+use crate::tags::{self, FieldError};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
-#[derive(Debug, Default)]
+use prost_reflect::{prost::Message as _, DescriptorPool, FileDescriptor, MessageDescriptor};
+use protobuf::{
+    descriptor::{FileDescriptorSet, SourceCodeInfo},
+    Message, UnknownFields, UnknownValueRef,
+};
+
+#[derive(Debug, Default, Clone, clap::Args)]
 pub struct DescriptionIds {
+    /// The ID of the option for FileDescriptor comments
+    #[clap(long = "file-id", value_name = "ID")]
     pub file: Option<u32>,
+    /// The ID of the option for Message comments
+    #[clap(long = "message-id", value_name = "ID")]
     pub message: Option<u32>,
+    /// The ID of the option for Enum comments
+    #[clap(long = "enum-id", value_name = "ID")]
     pub enum_: Option<u32>,
+    /// The ID of the option for Service comments
+    #[clap(long = "service-id", value_name = "ID")]
     pub service: Option<u32>,
+    /// The ID of the option for Method comments
+    #[clap(long = "method-id", value_name = "ID")]
     pub method: Option<u32>,
+    /// The ID of the option for Field comments
+    #[clap(long = "field-id", value_name = "ID")]
     pub field: Option<u32>,
+    /// The ID of the option for EnumValue comments
+    #[clap(long = "enum-value-id", value_name = "ID")]
     pub enum_value: Option<u32>,
+    /// The ID of the option for Extension comments
+    #[clap(long = "extension-id", value_name = "ID")]
     pub extension: Option<u32>,
+    /// The ID of the option for Oneof comments
+    #[clap(long = "oneof-id", value_name = "ID")]
     pub oneof: Option<u32>,
 }
-pub fn comments2option(res: &[u8], ids: &DescriptionIds) -> Vec<u8> {
-    let mut res = FileDescriptorSet::parse_from_bytes(res).unwrap();
+impl DescriptionIds {
+    /// True when no option ID is configured at all
+    pub fn is_empty(&self) -> bool {
+        [
+            self.file,
+            self.message,
+            self.enum_,
+            self.service,
+            self.method,
+            self.field,
+            self.enum_value,
+            self.extension,
+            self.oneof,
+        ]
+        .iter()
+        .all(Option::is_none)
+    }
+}
+
+/// What to do when an element already has a value for its description option
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ExistingPolicy {
+    /// Replace the existing value with the comment
+    Overwrite,
+    /// Keep the existing value, and ignore the comment
+    #[default]
+    Keep,
+    /// Add the comment as another value
+    Append,
+    /// Fail when the existing value differs from the comment
+    Error,
+}
+
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct Config {
+    #[clap(flatten)]
+    pub ids: DescriptionIds,
+    /// What to do with elements already having a description option, e.g. set in source.
+    /// A value equal to the comment is never duplicated, so that running twice is a no-op.
+    #[clap(long = "existing", value_enum, default_value_t)]
+    pub existing: ExistingPolicy,
+    /// Store descriptions as this doc message, e.g. `doc.Doc`, instead of strings.
+    /// Its `summary`, `body`, `deprecated`, `examples`, `since` and `see` fields are filled
+    /// from the comment and its `@deprecated`, `@example`, `@since` and `@see` tags.
+    #[clap(long = "structured", value_name = "FULL_NAME")]
+    pub structured: Option<String>,
+    /// Add a `protox_doc/doc.proto` file declaring the description extensions to the output,
+    /// so that the options can be decoded without other sources.
+    /// Extensions already declared in the descriptor set are not declared again.
+    /// With `--structured`, they must all be declared already, with the doc message type.
+    #[clap(long = "embed-doc-proto")]
+    pub embed_doc_proto: bool,
+    /// Take the descriptions from this JSON or YAML file mapping full names to doc texts,
+    /// e.g. as written by extract, instead of the comments.
+    #[clap(long = "sidecar", value_name = "PATH")]
+    pub sidecar: Option<PathBuf>,
+    /// Rewrite the references to other elements, as `[Bar]` or `` `Bar.baz` ``, into their
+    /// full name, resolving them from the documented element like protoc resolves type names.
+    #[clap(long = "qualify-references")]
+    pub qualify_references: bool,
+}
+
+/// The name of the file declaring the description extensions, see [`Config::embed_doc_proto`]
+pub const DOC_PROTO: &str = "protox_doc/doc.proto";
+
+pub fn comments2option(res: &[u8], config: &Config) -> Result<Vec<u8>> {
+    let bytes = res;
+    let mut res = FileDescriptorSet::parse_from_bytes(bytes).map_err(Error::Decode)?;
+    // a set encoded without its imports is fine, unless names have to be resolved
+    let needs_pool = config.structured.is_some()
+        || config.sidecar.is_some()
+        || config.qualify_references
+        || config.embed_doc_proto;
+    let pool = match DescriptorPool::decode(bytes) {
+        Ok(pool) => Some(pool),
+        Err(err) if needs_pool => return Err(Error::InvalidDescriptors(err)),
+        Err(_) => None,
+    };
+    let repeated = repeated_options(pool.as_ref());
+    let doc = match &config.structured {
+        Some(name) => Some(
+            pool.as_ref()
+                .and_then(|pool| pool.get_message_by_name(name.trim_start_matches('.')))
+                .ok_or_else(|| Error::UnknownDocMessage { name: name.clone() })?,
+        ),
+        None => None,
+    };
+    let sidecar = match (&config.sidecar, &pool) {
+        (Some(path), Some(pool)) => Some((path, sidecar::load(path)?, pool)),
+        _ => None,
+    };
+    let references = match (config.qualify_references, &pool) {
+        (true, Some(pool)) => Some((Names::new(pool), pool)),
+        _ => None,
+    };
+    if let Some((path, sidecar, pool)) = &sidecar {
+        let known: Vec<String> = res
+            .file
+            .iter()
+            .filter(|file| !file.name().starts_with("google"))
+            .filter_map(|file| pool.get_file_by_name(file.name()))
+            .flat_map(|fd| {
+                let names: Vec<String> = elements(&fd)
+                    .iter()
+                    .map(|(_, pathed)| describe(pathed).1)
+                    .collect();
+                std::iter::once(fd.name().to_string()).chain(names)
+            })
+            .collect();
+        sidecar::check_names(sidecar, &known, path)?;
+    }
     for file in &mut res.file {
         if file.name().starts_with("google") {
             continue;
         }
-        let sci = file.source_code_info.clone();
-        for loc in sci.location.iter() {
-            let comments = if loc.has_leading_comments() {
-                loc.leading_comments.as_ref().unwrap().clone()
-            } else if loc.has_trailing_comments() {
-                loc.trailing_comments.as_ref().unwrap().clone()
-            } else {
-                continue;
+        let comments = match &sidecar {
+            Some((_, sidecar, pool)) => match pool.get_file_by_name(file.name()) {
+                Some(fd) => sidecar_comments(&fd, &file.source_code_info, sidecar),
+                None => continue,
+            },
+            None => source_comments(&file.source_code_info),
+        };
+        let scopes = match &references {
+            Some((_, pool)) => pool.get_file_by_name(file.name()).map(|fd| scopes(&fd)),
+            None => None,
+        };
+        for mut comment in comments {
+            if let (Some((names, _)), Some(scopes)) = (&references, &scopes) {
+                if let Some(scope) = scopes.get(&comment.path) {
+                    comment.text = qualify(&comment.text, scope, names);
+                }
+            }
+            let target = Target {
+                config,
+                repeated: &repeated,
+                doc: doc.as_ref(),
+                file: file.name().to_string(),
+                line: comment.line,
             };
-            let comments = process_markdown_like_whitespace(&comments);        
-            let comments = comments.trim().to_string();
-            if let Some(pathed) = file.get_child_from_loc(loc) {
-                insert_comment(pathed, comments, ids);
+            let mut path = comment.path.into();
+            if let Some(pathed) = file.get_child_from_path(&mut path) {
+                insert_comment(pathed, comment.text, &target)?;
+            }
+        }
+    }
+    if let (true, Some(pool)) = (config.embed_doc_proto, &pool) {
+        embed_doc_proto(&mut res, &config.ids, doc.as_ref(), pool)?;
+    }
+    res.write_to_bytes().map_err(Error::Encode)
+}
+/// The full names of the elements of `fd` by path, the file statements being in the package
+fn scopes(fd: &FileDescriptor) -> HashMap<Vec<i32>, String> {
+    let package = fd.package_name().to_string();
+    elements(fd)
+        .iter()
+        .map(|(path, pathed)| (path.clone(), describe(pathed).1))
+        .chain([
+            (vec![tag::file::SYNTAX], package.clone()),
+            (vec![tag::file::PACKAGE], package),
+        ])
+        .collect()
+}
+/// A comment to store as the description of the element at `path`
+struct Comment {
+    path: Vec<i32>,
+    text: String,
+    /// The line of the element, starting at 1, for error messages
+    line: usize,
+}
+/// The leading (or trailing) comments of the elements of a file
+fn source_comments(sci: &SourceCodeInfo) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut file_described = false;
+    for loc in sci.location.iter() {
+        let is_file = loc.path == [tag::file::SYNTAX] || loc.path == [tag::file::PACKAGE];
+        let text = if is_file {
+            if file_described {
+                continue;
             }
+            // keep detached comments (e.g. a header) as separate paragraphs
+            let mut comments = loc.leading_detached_comments.clone();
+            comments.extend(loc.leading_comments.clone());
+            if comments.is_empty() {
+                continue;
+            }
+            file_described = true;
+            comments
+                .iter()
+                .map(|comment| strip_comment_markers(comment))
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else if loc.has_leading_comments() {
+            strip_comment_markers(loc.leading_comments())
+        } else if loc.has_trailing_comments() {
+            strip_comment_markers(loc.trailing_comments())
+        } else {
+            continue;
+        };
+        comments.push(Comment {
+            path: loc.path.clone(),
+            text,
+            line: loc.span[0] as usize + 1,
+        });
+    }
+    comments
+}
+/// The descriptions of the elements of `fd` found in `sidecar`, laid out as comments
+fn sidecar_comments(fd: &FileDescriptor, sci: &SourceCodeInfo, sidecar: &Sidecar) -> Vec<Comment> {
+    let line = |path: &[i32]| {
+        sci.location
+            .iter()
+            .find(|loc| loc.path == path)
+            .map_or(0, |loc| loc.span[0] as usize + 1)
+    };
+    let as_comment = |description: &str| {
+        markdown::render(description, usize::MAX)
+            .iter()
+            .map(|line| format!(" {}", line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let mut comments = Vec::new();
+    if let Some(description) = sidecar.get(fd.name()) {
+        let path = vec![tag::file::SYNTAX];
+        comments.push(Comment {
+            line: line(&path),
+            path,
+            text: as_comment(description),
+        });
+    }
+    for (path, pathed) in elements(fd) {
+        if let Some(description) = sidecar.get(&describe(&pathed).1) {
+            comments.push(Comment {
+                line: line(&path),
+                path,
+                text: as_comment(description),
+            });
+        }
+    }
+    comments
+}
+/// Add the declaration of the description extensions of `ids` which are missing from `res`,
+/// along with `google/protobuf/descriptor.proto` when needed
+/// The declared extensions are strings, so those of structured descriptions must be in `res`.
+fn embed_doc_proto(
+    res: &mut FileDescriptorSet,
+    ids: &DescriptionIds,
+    doc: Option<&MessageDescriptor>,
+    pool: &DescriptorPool,
+) -> Result<()> {
+    let declared: HashSet<(String, u32)> = pool
+        .all_extensions()
+        .map(|ext| (ext.containing_message().full_name().to_string(), ext.number()))
+        .collect();
+    let missing = |options: &str, id: Option<u32>| {
+        let id = id.filter(|id| !declared.contains(&(format!("google.protobuf.{}", options), *id)));
+        match (doc, id) {
+            (Some(doc), Some(_)) => Err(Error::EmbedStructured {
+                message: doc.full_name().to_string(),
+                options: options.to_string(),
+            }),
+            _ => Ok(id),
+        }
+    };
+    let ids = DescriptionIds {
+        file: missing("FileOptions", ids.file)?,
+        message: missing("MessageOptions", ids.message)?,
+        enum_: missing("EnumOptions", ids.enum_)?,
+        service: missing("ServiceOptions", ids.service)?,
+        method: missing("MethodOptions", ids.method)?,
+        field: missing("FieldOptions", ids.field)?,
+        enum_value: missing("EnumValueOptions", ids.enum_value)?,
+        extension: missing("FieldOptions", ids.extension)?,
+        oneof: missing("OneofOptions", ids.oneof)?,
+    };
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let set = gen_doc_proto::doc_descriptor_set(&ids, "protox_doc", DOC_PROTO, true)?;
+    let set = FileDescriptorSet::parse_from_bytes(&set).map_err(Error::Decode)?;
+    for file in set.file {
+        if !res.file.iter().any(|f| f.name() == file.name()) {
+            res.file.push(file);
         }
     }
-    res.write_to_bytes().unwrap()
+    Ok(())
+}
+/// The repeated extensions of the descriptor set, by options message name and number
+fn repeated_options(pool: Option<&DescriptorPool>) -> HashSet<(String, u32)> {
+    // without their definitions, e.g. when the imports are missing, options are taken as singular
+    let Some(pool) = pool else {
+        return HashSet::new();
+    };
+    pool.all_extensions()
+        .filter(|ext| ext.is_list())
+        .map(|ext| (ext.containing_message().full_name().to_string(), ext.number()))
+        .collect()
+}
+/// How to store a comment, and where it comes from
+struct Target<'a> {
+    config: &'a Config,
+    repeated: &'a HashSet<(String, u32)>,
+    /// The doc message of structured descriptions
+    doc: Option<&'a MessageDescriptor>,
+    file: String,
+    line: usize,
+}
+impl Target<'_> {
+    /// The values to store for `comment` in the description option `id` of `options`
+    fn values(&self, comment: &str, options: &str, id: u32) -> Result<Vec<Vec<u8>>> {
+        if let Some(doc) = self.doc {
+            let message = tags::to_message(&tags::parse(comment), doc).map_err(|err| {
+                let message = doc.full_name().to_string();
+                let (file, line) = (self.file.clone(), self.line);
+                match err {
+                    FieldError::Missing(field) => Error::MissingDocField {
+                        message,
+                        field,
+                        file,
+                        line,
+                    },
+                    FieldError::NotText { part, field } => Error::DocFieldType {
+                        message,
+                        field,
+                        part,
+                        file,
+                        line,
+                    },
+                }
+            })?;
+            return Ok(vec![message.encode_to_vec()]);
+        }
+        let description = process_markdown_like_whitespace(comment);
+        let description = description.trim();
+        if self.repeated.contains(&(options.to_string(), id)) {
+            // repeated descriptions get one paragraph per value, code blocks and tables stay whole
+            return Ok(markdown::blocks(description)
+                .iter()
+                .map(|block| block.text().into_bytes())
+                .collect());
+        }
+        Ok(vec![description.as_bytes().to_vec()])
+    }
 }
 macro_rules! insert_comment {
-    ($x: ident, $comment: ident, $id: expr) => {
+    ($x: ident, $comment: ident, $id: expr, $options: literal, $target: ident) => {
         if let Some(id) = $id {
-            $x.options
+            let name = $x.name().to_string();
+            let values = $target.values(&$comment, $options, id)?;
+            let fields = $x
+                .options
                 .mut_or_insert_default()
                 .special_fields
-                .mut_unknown_fields()
-                .add_length_delimited(id, $comment);
+                .mut_unknown_fields();
+            set_description(fields, id, values, &name, $target)?;
         }
     };
 }
-fn insert_comment(pathed: PathedDescriptor, comment: String, ids: &DescriptionIds) {
-    let comment = comment.as_bytes().to_vec();
+fn insert_comment(pathed: PathedDescriptor, comment: String, target: &Target) -> Result<()> {
+    let ids = &target.config.ids;
     match pathed {
+        PathedDescriptor::File(file) => {
+            insert_comment!(file, comment, ids.file, "google.protobuf.FileOptions", target);
+        }
         PathedDescriptor::Message(message) => {
-            insert_comment!(message, comment, ids.message);
+            insert_comment!(message, comment, ids.message, "google.protobuf.MessageOptions", target);
         }
         PathedDescriptor::Enum(enum_) => {
-            insert_comment!(enum_, comment, ids.enum_);
+            insert_comment!(enum_, comment, ids.enum_, "google.protobuf.EnumOptions", target);
         }
         PathedDescriptor::Service(service) => {
-            insert_comment!(service, comment, ids.service);
+            insert_comment!(service, comment, ids.service, "google.protobuf.ServiceOptions", target);
         }
         PathedDescriptor::Method(method) => {
-            insert_comment!(method, comment, ids.method);
+            insert_comment!(method, comment, ids.method, "google.protobuf.MethodOptions", target);
         }
         PathedDescriptor::Field(field) => {
-            insert_comment!(field, comment, ids.field);
+            insert_comment!(field, comment, ids.field, "google.protobuf.FieldOptions", target);
         }
         PathedDescriptor::EnumValue(enum_value) => {
-            insert_comment!(enum_value, comment, ids.enum_value);
+            insert_comment!(enum_value, comment, ids.enum_value, "google.protobuf.EnumValueOptions", target);
         }
         PathedDescriptor::Extension(extension) => {
-            insert_comment!(extension, comment, ids.extension);
+            insert_comment!(extension, comment, ids.extension, "google.protobuf.FieldOptions", target);
         }
         PathedDescriptor::Oneof(oneof) => {
-            insert_comment!(oneof, comment, ids.oneof);
+            insert_comment!(oneof, comment, ids.oneof, "google.protobuf.OneofOptions", target);
         }
     }
+    Ok(())
 }
-fn process_markdown_like_whitespace(input: &str) -> String {
-    let mut result = String::new();
-    let mut iter = input.chars().peekable();
-
-    while let Some(&c) = iter.peek() {
-        if c == '\n' {
-            iter.next(); // Consume the newline
-            if let Some(&next_char) = iter.peek() {
-                match next_char {
-                    '\n' => {
-                        iter.next(); // Consume the second newline
-                        result.push('\n');
-                    }
-                    _ => {
-                        if !result.ends_with(' ') {
-                            result.push(' ');
-                        }
-                    }
-                }
-            } else { // last character
-                result.push(' ');
+/// Store `values` as the description option `id` of the element `name`,
+/// according to the policy for existing values
+/// Custom options are not known to the descriptor messages, so values set in source are found
+/// among the unknown fields, like those added by a previous run.
+fn set_description(
+    fields: &mut UnknownFields,
+    id: u32,
+    values: Vec<Vec<u8>>,
+    name: &str,
+    target: &Target,
+) -> Result<()> {
+    let existing: Vec<Vec<u8>> = fields
+        .iter()
+        .filter(|(number, _)| *number == id)
+        .map(|(_, value)| match value {
+            UnknownValueRef::LengthDelimited(bytes) => bytes.to_vec(),
+            _ => Vec::new(),
+        })
+        .collect();
+    let add = |fields: &mut UnknownFields| {
+        for value in values.clone() {
+            fields.add_length_delimited(id, value);
+        }
+    };
+    if existing.is_empty() {
+        add(fields);
+        return Ok(());
+    }
+    let contained = existing.windows(values.len()).any(|window| window == values);
+    match target.config.existing {
+        ExistingPolicy::Overwrite => {
+            if existing != values {
+                fields.remove(id);
+                add(fields);
             }
-            if iter.peek() == Some(&' ') {
-                iter.next(); // Consume the whitespace
+        }
+        ExistingPolicy::Keep => {}
+        ExistingPolicy::Append => {
+            if !contained {
+                add(fields);
             }
-
+        }
+        ExistingPolicy::Error => {
+            if !contained {
+                return Err(Error::ExistingDescription {
+                    name: name.to_string(),
+                    id,
+                    file: target.file.clone(),
+                    line: target.line,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+/// Remove what is left of the markers of `///` and `/** */` comments,
+/// i.e. the extra `/` starting each line, or the extra `*` starting the comment
+pub(crate) fn strip_comment_markers(comment: &str) -> String {
+    if let Some(rest) = comment.strip_prefix('*') {
+        return rest.to_string();
+    }
+    let doc = comment.lines().all(|line| line.is_empty() || line.starts_with('/'));
+    if !doc {
+        return comment.to_string();
+    }
+    comment
+        .lines()
+        .map(|line| line.strip_prefix('/').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+/// Turn a comment into a description: prose lines of a paragraph are joined,
+/// while each paragraph, list item and verbatim line (code, tables) gets its own line
+pub(crate) fn process_markdown_like_whitespace(input: &str) -> String {
+    let mut result: Vec<String> = Vec::new();
+    // the paragraph or list item being built
+    let mut current: Option<String> = None;
+    let mut in_fence = false;
+    for line in input.lines() {
+        // comments usually start with a space after the `//`
+        let line = line.strip_prefix(' ').unwrap_or(line);
+        if in_fence {
+            in_fence = !markdown::is_fence(line);
+            result.push(line.to_string());
+        } else if markdown::is_fence(line) {
+            result.extend(current.take());
+            in_fence = true;
+            result.push(line.to_string());
+        } else if line.trim().is_empty() {
+            result.extend(current.take());
+        } else if markdown::list_prefix_len(line).is_some() {
+            result.extend(current.take());
+            current = Some(line.trim_end().to_string());
+        } else if current.is_none() && (markdown::is_table_row(line) || markdown::is_indented_code(line)) {
+            result.push(line.trim_end().to_string());
+        } else if let Some(current) = current.as_mut() {
+            current.push(' ');
+            current.push_str(line.trim());
         } else {
-            result.push(c);
-            iter.next();
+            current = Some(line.trim().to_string());
         }
     }
-
-    result
+    result.extend(current);
+    result.join("\n")
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
-
     use super::*;
-    use protox::Compiler;
     use crate::tests::compare_fds::compare_fds;
+    use crate::tests::fixtures::{compile, fixtures, test_ids};
 
     fn comments2option_test(fixture: &str) {
-        let ids = DescriptionIds {
-            file: Some(1000),
-            message: Some(1000),
-            enum_: Some(1000),
-            service: Some(1000),
-            method: Some(1000),
-            field: Some(1000),
-            enum_value: Some(1000),
-            extension: Some(1000),
-            oneof: Some(1000),
-        };
-        let mut fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        fixtures.push("src/fixtures");
-        let path = fixtures.join(fixture).with_extension("expected.proto");
-        let mut c = Compiler::new(vec![fixtures.clone()]).unwrap();
-        c.include_imports(true);
-        c.include_source_info(true);
-        c.open_file(path).unwrap();
-        let v = c.encode_file_descriptor_set();
-        let res = comments2option(&v, &ids);
-        let path = fixtures.join(fixture);
-        let mut c = Compiler::new(vec![fixtures.clone()]).unwrap();
-        c.include_imports(true);
+        comments2option_test_with(fixture, "expected.proto");
+    }
+    fn test_config() -> Config {
+        Config {
+            ids: test_ids(),
+            ..Default::default()
+        }
+    }
+    /// `expected` is the extension of the commented file to convert back to `fixture`
+    fn comments2option_test_with(fixture: &str, expected: &str) {
+        comments2option_test_config(fixture, expected, &test_config());
+    }
+    fn comments2option_test_config(fixture: &str, expected: &str, config: &Config) {
+        let commented = PathBuf::from(fixture).with_extension(expected);
+        let v = compile_fixture(commented.to_str().unwrap());
+        let res = comments2option(&v, config).unwrap();
+        let mut c = compile(fixture);
         c.include_source_info(false);
-        c.open_file(path).unwrap();
         let expected = c.encode_file_descriptor_set();
         compare_fds(&expected, &res, fixture);
     }
@@ -163,7 +587,340 @@ mod tests {
     }
     #[test]
     fn test_multiline() {
-        comments2option_test("multiline.proto");
+        // the `// 🚀` comment above `syntax` is not a file description in this fixture,
+        // the ids of its tests have no file id
+        let mut config = test_config();
+        config.ids.file = None;
+        comments2option_test_config("multiline.proto", "expected.proto", &config);
+    }
+    #[test]
+    fn test_oneof_extension() {
+        comments2option_test("oneof_extension.proto");
+    }
+    #[test]
+    fn test_file() {
+        comments2option_test("file.proto");
+    }
+    #[test]
+    fn test_syntax_comment() {
+        comments2option_test("syntax_comment.proto");
+    }
+    #[test]
+    fn test_markdown() {
+        comments2option_test("markdown.proto");
+    }
+    #[test]
+    fn test_doc_style() {
+        comments2option_test_with("basic.proto", "doc.expected.proto");
+    }
+    #[test]
+    fn test_block_style() {
+        comments2option_test_with("markdown.proto", "block.expected.proto");
+    }
+    fn compile_fixture(fixture: &str) -> Vec<u8> {
+        compile(fixture).encode_file_descriptor_set()
+    }
+    /// The descriptions of the message and of its field in existing.proto, with `policy`
+    fn existing_test(policy: ExistingPolicy) -> Result<(Vec<String>, Vec<String>)> {
+        let config = Config {
+            existing: policy,
+            ..test_config()
+        };
+        let res = comments2option(&compile_fixture("existing.proto"), &config)?;
+        let res = FileDescriptorSet::parse_from_bytes(&res).unwrap();
+        let file = res.file.iter().find(|f| f.name() == "existing.proto").unwrap();
+        let values = |fields: &UnknownFields| {
+            fields
+                .iter()
+                .filter(|(number, _)| *number == 1000)
+                .map(|(_, value)| match value {
+                    UnknownValueRef::LengthDelimited(bytes) => {
+                        String::from_utf8(bytes.to_vec()).unwrap()
+                    }
+                    _ => panic!("unexpected value"),
+                })
+                .collect::<Vec<_>>()
+        };
+        let message = &file.message_type[0];
+        Ok((
+            values(message.options.special_fields.unknown_fields()),
+            values(message.field[0].options.special_fields.unknown_fields()),
+        ))
+    }
+    #[test]
+    fn test_existing() {
+        let field = vec!["This is a field documentation.".to_string()];
+        let (message, field_) = existing_test(ExistingPolicy::Keep).unwrap();
+        assert_eq!(message, vec!["This is a message option."]);
+        assert_eq!(field_, field);
+        let (message, field_) = existing_test(ExistingPolicy::Overwrite).unwrap();
+        assert_eq!(message, vec!["This is a message comment."]);
+        assert_eq!(field_, field);
+        let (message, field_) = existing_test(ExistingPolicy::Append).unwrap();
+        assert_eq!(message, vec!["This is a message option.", "This is a message comment."]);
+        assert_eq!(field_, field);
+        let err = existing_test(ExistingPolicy::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "existing.proto:6: MyMessage already has a different description option 1000"
+        );
+    }
+    #[test]
+    fn test_repeated_target() {
+        let config = Config {
+            ids: DescriptionIds {
+                field: Some(1002),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let res = comments2option(&compile_fixture("typed.expected.proto"), &config).unwrap();
+        let res = FileDescriptorSet::parse_from_bytes(&res).unwrap();
+        let file = res.file.iter().find(|f| f.name() == "typed.expected.proto").unwrap();
+        let fields = &file.message_type[1].field;
+        let values = |field: usize| {
+            fields[field]
+                .options
+                .special_fields
+                .unknown_fields()
+                .iter()
+                .map(|(_, value)| match value {
+                    UnknownValueRef::LengthDelimited(bytes) => {
+                        String::from_utf8(bytes.to_vec()).unwrap()
+                    }
+                    _ => panic!("unexpected value"),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            values(0),
+            vec!["This is a first paragraph.", "This is a second paragraph."]
+        );
+        assert_eq!(values(1), vec!["This is a single paragraph."]);
+    }
+    #[test]
+    fn test_repeated_values() {
+        let config = test_config();
+        let repeated = HashSet::from([("google.protobuf.FieldOptions".to_string(), 1000)]);
+        let target = Target {
+            config: &config,
+            repeated: &repeated,
+            doc: None,
+            file: "test.proto".to_string(),
+            line: 1,
+        };
+        let comment = " A paragraph\n on two lines.\n\n ```\n let a = 1;\n\n let b = 2;\n ```\n - an item\n";
+        let values = target
+            .values(comment, "google.protobuf.FieldOptions", 1000)
+            .unwrap();
+        let values: Vec<String> = values
+            .into_iter()
+            .map(|value| String::from_utf8(value).unwrap())
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                "A paragraph on two lines.",
+                "```\nlet a = 1;\n\nlet b = 2;\n```",
+                "- an item"
+            ]
+        );
+    }
+    fn structured_config() -> Config {
+        Config {
+            ids: DescriptionIds {
+                message: Some(1003),
+                field: Some(1003),
+                ..Default::default()
+            },
+            structured: Some("test.Doc".to_string()),
+            ..Default::default()
+        }
+    }
+    #[test]
+    fn test_structured() {
+        comments2option_test_config("structured.proto", "expected.proto", &structured_config());
+    }
+    #[test]
+    fn test_structured_errors() {
+        let res = compile_fixture("structured.expected.proto");
+        let config = Config {
+            structured: Some("test.Missing".to_string()),
+            ..structured_config()
+        };
+        let err = comments2option(&res, &config).unwrap_err();
+        assert_eq!(err.to_string(), "the doc message test.Missing is not in the descriptor set");
+        let config = Config {
+            structured: Some("test.ShortDoc".to_string()),
+            ..structured_config()
+        };
+        let err = comments2option(&res, &config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "structured.expected.proto:39: the doc message test.ShortDoc has no `body` field"
+        );
+    }
+    #[test]
+    fn test_sidecar() {
+        let fixtures = fixtures();
+        let config = Config {
+            sidecar: Some(fixtures.join("sidecar.yaml")),
+            ..test_config()
+        };
+        let res = comments2option(&compile_fixture("sidecar.proto"), &config).unwrap();
+        let mut c = compile("sidecar.options.proto");
+        c.include_source_info(false);
+        compare_fds(&c.encode_file_descriptor_set(), &res, "sidecar.proto");
+    }
+    #[test]
+    fn test_qualify_references() {
+        use crate::extract::{extract, DocSource, Docs};
+        use crate::option2comments::DescriptionSelector;
+        let config = Config {
+            embed_doc_proto: true,
+            qualify_references: true,
+            ..test_config()
+        };
+        let res = comments2option(&compile_fixture("references.proto"), &config).unwrap();
+        let pool = DescriptorPool::decode(res.as_slice()).unwrap();
+        let fd = pool.get_file_by_name("references.proto").unwrap();
+        let mut docs = Docs::new();
+        extract(&fd, DocSource::Options, &DescriptionSelector::default(), &mut docs).unwrap();
+        let doc = |name: &str| docs[name].doc.as_str();
+        assert_eq!(
+            doc("refs.v1.Catalog"),
+            "A list of [refs.v1.Catalog.Item], named by `refs.v1.Catalog.Item.name`."
+        );
+        assert_eq!(
+            doc("refs.v1.Catalog.items"),
+            "The items, see [refs.v1.Item] and [refs.v1.Item.Color]."
+        );
+        // dangling references are kept as they are
+        assert_eq!(
+            doc("refs.v1.Catalog.first"),
+            "The first of the items, or [Missing] (see [the guide](https://example.com)), \
+             with its `Item.title`."
+        );
+        assert_eq!(
+            doc("refs.v1.Item.name"),
+            "Its name, for example:\n```\n[NotAReference]\n```"
+        );
+        assert_eq!(
+            doc("refs.v1.Item.color"),
+            "Its color, [refs.v1.Item.COLOR_RED] by default."
+        );
+    }
+    #[test]
+    fn test_embed_doc_proto() {
+        let config = Config {
+            embed_doc_proto: true,
+            ..test_config()
+        };
+        let res = comments2option(&compile_fixture("plain.proto"), &config).unwrap();
+        let pool = DescriptorPool::decode(res.as_slice()).unwrap();
+        let description = |options: prost_reflect::DynamicMessage, name: &str| {
+            let ext = pool.get_extension_by_name(name).unwrap();
+            options.get_extension(&ext).as_str().unwrap().to_string()
+        };
+        let message = pool.get_message_by_name("test.MyMessage").unwrap();
+        assert_eq!(
+            description(message.options(), "protox_doc.message_description"),
+            "This is a message documentation."
+        );
+        let field = message.get_field_by_name("my_field").unwrap();
+        assert_eq!(
+            description(field.options(), "protox_doc.field_description"),
+            "This is a field documentation."
+        );
+        // extensions declared by the input are not declared again
+        let res = comments2option(&compile_fixture("basic.expected.proto"), &config).unwrap();
+        let pool = DescriptorPool::decode(res.as_slice()).unwrap();
+        let mut embedded: Vec<String> = pool
+            .all_extensions()
+            .filter(|ext| ext.parent_file().name() == DOC_PROTO)
+            .map(|ext| ext.name().to_string())
+            .collect();
+        embedded.sort();
+        assert_eq!(embedded, vec!["method_description", "service_description"]);
+    }
+    #[test]
+    fn test_embed_structured() {
+        // the extensions of the doc message are declared, there is nothing to embed
+        let config = Config {
+            embed_doc_proto: true,
+            ..structured_config()
+        };
+        let res = compile_fixture("structured.expected.proto");
+        assert!(comments2option(&res, &config).is_ok());
+        // an undeclared one would be declared as a string
+        let config = Config {
+            ids: DescriptionIds {
+                enum_: Some(1003),
+                ..config.ids
+            },
+            ..config
+        };
+        assert_eq!(
+            comments2option(&res, &config).unwrap_err().to_string(),
+            "the EnumOptions description option is not declared, and --embed-doc-proto only \
+             declares strings, not test.Doc"
+        );
+    }
+    #[test]
+    fn test_idempotent() {
+        for policy in [
+            ExistingPolicy::Overwrite,
+            ExistingPolicy::Keep,
+            ExistingPolicy::Append,
+            ExistingPolicy::Error,
+        ] {
+            let config = Config {
+                existing: policy,
+                ..test_config()
+            };
+            let once = comments2option(&compile_fixture("merge.expected.proto"), &config).unwrap();
+            let twice = comments2option(&once, &config).unwrap();
+            assert_eq!(once, twice, "{:?}", policy);
+        }
+    }
+    #[test]
+    fn test_invalid_descriptor_set() {
+        let err = comments2option(b"not a descriptor set", &test_config()).unwrap_err();
+        assert!(matches!(err, Error::Decode(_)));
+        // a set which decodes, but whose file imports a missing one
+        let mut file = protobuf::descriptor::FileDescriptorProto::new();
+        file.set_name("test.proto".to_string());
+        file.dependency.push("missing.proto".to_string());
+        let mut set = FileDescriptorSet::new();
+        set.file.push(file);
+        let config = Config {
+            structured: Some("doc.Doc".to_string()),
+            ..test_config()
+        };
+        let bytes = set.write_to_bytes().unwrap();
+        let err = comments2option(&bytes, &config).unwrap_err();
+        assert!(matches!(err, Error::InvalidDescriptors(_)));
+        // without an option resolving names, the pool is not needed
+        assert!(comments2option(&bytes, &test_config()).is_ok());
+    }
+    #[test]
+    fn test_strip_comment_markers() {
+        assert_eq!(strip_comment_markers("/ doc style\n/ second\n"), " doc style\n second");
+        assert_eq!(strip_comment_markers("*\n block style\n\n second\n"), "\n block style\n\n second\n");
+        assert_eq!(strip_comment_markers("* one line "), " one line ");
+        assert_eq!(strip_comment_markers(" plain /path\n"), " plain /path\n");
+    }
+    #[test]
+    fn test_process_markdown_like_whitespace() {
+        let comment = " Intro\n on two lines.\n\n - an item\n   continued\n - b\n\n ```\n   code\n\n ```\n | a |\n | 1 |\n";
+        assert_eq!(
+            process_markdown_like_whitespace(comment),
+            "Intro on two lines.\n- an item continued\n- b\n```\n  code\n\n```\n| a |\n| 1 |"
+        );
+    }
+    #[test]
+    fn test_file_header() {
+        comments2option_test("file_header.proto");
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,348 @@
+//! Documentation coverage: the share of elements having a comment or a description option.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+};
+
+use prost_reflect::{DescriptorPool, FileDescriptor};
+
+use crate::error::{Error, Result};
+use crate::extract::{extract, DocSource, Docs, Input};
+use crate::findings::{render, Finding, Format, RuleInfo, Span};
+use crate::option2comments::{describe, DescriptionSelector};
+use crate::path_resolver::prost::elements;
+
+#[derive(Debug, Default, clap::Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub(crate) input: Input,
+    /// Fail when the coverage of all the files, in percent, is below this value.
+    #[clap(long = "min-coverage", value_name = "PERCENT")]
+    pub(crate) min_coverage: Option<f64>,
+    /// Fail when elements missing from this encoded FileDescriptorSet, e.g. the one of the last
+    /// release, are not documented. Elements are matched by full name.
+    #[clap(long = "baseline", value_name = "PATH", value_parser)]
+    pub(crate) baseline: Option<PathBuf>,
+    /// The format of the output: `text` is the coverage report, and the other formats list the
+    /// undocumented elements (the new ones with `--baseline`).
+    #[clap(long = "format", value_enum, default_value_t)]
+    pub(crate) format: Format,
+    #[clap(flatten)]
+    pub(crate) selector: DescriptionSelector,
+}
+
+/// An element without documentation
+#[derive(Debug, PartialEq)]
+pub struct Missing {
+    pub kind: &'static str,
+    pub name: String,
+    /// The span of the element, when known
+    pub span: Option<Span>,
+}
+
+/// The documentation coverage of a file
+#[derive(Debug, Default, PartialEq)]
+pub struct FileCoverage {
+    pub package: String,
+    pub total: usize,
+    pub missing: Vec<Missing>,
+}
+
+/// The coverage of the files, by name
+#[derive(Debug, Default, PartialEq)]
+pub struct Coverage {
+    pub files: BTreeMap<String, FileCoverage>,
+}
+
+pub fn entry_point(args: Args) -> Result<()> {
+    let (pool, files) = args.input.load()?;
+    let mut coverage = Coverage::default();
+    for name in files {
+        let fd = pool
+            .get_file_by_name(&name)
+            .ok_or_else(|| Error::MissingFile { name: name.clone() })?;
+        coverage.add(&fd, &args.selector)?;
+    }
+    let new = match &args.baseline {
+        Some(path) => {
+            let bytes = std::fs::read(path).map_err(Error::io(path))?;
+            let baseline =
+                DescriptorPool::decode(bytes.as_slice()).map_err(Error::InvalidDescriptors)?;
+            Some(coverage.new_missing(&names(&baseline)))
+        }
+        None => None,
+    };
+    if args.format == Format::Text {
+        print!("{}", coverage.report());
+        for (file, missing) in new.iter().flatten() {
+            println!(
+                "{}{}: {} {} is new and not documented",
+                file,
+                line_suffix(missing),
+                missing.kind,
+                missing.name
+            );
+        }
+    } else {
+        let findings = match &new {
+            Some(new) => new.iter().map(|(file, missing)| finding(file, missing)).collect(),
+            None => coverage.findings(),
+        };
+        let root = args.input.source_root();
+        print!("{}", render(&findings, args.format, &[rule()], root));
+    }
+    if let Some(new) = new.filter(|new| !new.is_empty()) {
+        return Err(Error::NewUndocumented { count: new.len() });
+    }
+    let percent = coverage.percent();
+    match args.min_coverage {
+        Some(min) if percent < min => Err(Error::LowCoverage { percent, min }),
+        _ => Ok(()),
+    }
+}
+
+/// The rule of the undocumented elements, in findings
+const UNDOCUMENTED: &str = "undocumented";
+
+fn rule() -> RuleInfo {
+    RuleInfo {
+        id: UNDOCUMENTED,
+        description: "Elements have a comment or a description option".to_string(),
+    }
+}
+
+/// The finding of an undocumented element of `file`
+fn finding(file: &str, missing: &Missing) -> Finding {
+    Finding {
+        rule: UNDOCUMENTED,
+        file: file.to_string(),
+        span: missing.span,
+        name: missing.name.clone(),
+        message: format!("{} {} is not documented", missing.kind, missing.name),
+    }
+}
+
+/// `:line` when the line of `missing` is known
+fn line_suffix(missing: &Missing) -> String {
+    missing
+        .span
+        .map(|span| format!(":{}", span.start_line))
+        .unwrap_or_default()
+}
+
+/// The full names of the elements of `pool`
+pub fn names(pool: &DescriptorPool) -> HashSet<String> {
+    pool.files()
+        .flat_map(|fd| {
+            let names: Vec<String> = elements(&fd)
+                .iter()
+                .map(|(_, pathed)| describe(pathed).1)
+                .collect();
+            names
+        })
+        .collect()
+}
+
+impl Coverage {
+    /// Add the elements of `fd`: messages, fields, oneofs, enums, enum values, services and methods
+    /// An element is documented by a leading or trailing comment, or by a description option.
+    pub fn add(&mut self, fd: &FileDescriptor, selector: &DescriptionSelector) -> Result<()> {
+        let mut docs = Docs::new();
+        extract(fd, DocSource::Comments, selector, &mut docs)?;
+        extract(fd, DocSource::Options, selector, &mut docs)?;
+        let spans: BTreeMap<Vec<i32>, Span> = fd
+            .file_descriptor_proto()
+            .source_code_info
+            .iter()
+            .flat_map(|info| &info.location)
+            .filter_map(|loc| Some((loc.path.clone(), Span::new(loc)?)))
+            .collect();
+        let file = self.files.entry(fd.name().to_string()).or_default();
+        file.package = fd.package_name().to_string();
+        for (path, pathed) in elements(fd) {
+            let (kind, name) = describe(&pathed);
+            if kind == "extension" {
+                continue;
+            }
+            file.total += 1;
+            if docs.get(&name).is_some_and(|entry| !entry.doc.is_empty()) {
+                continue;
+            }
+            file.missing.push(Missing {
+                kind,
+                name,
+                span: spans.get(&path).copied(),
+            });
+        }
+        file.missing.sort_by_key(|missing| missing.span);
+        Ok(())
+    }
+    /// The undocumented elements, as findings
+    pub fn findings(&self) -> Vec<Finding> {
+        self.files
+            .iter()
+            .flat_map(|(name, file)| file.missing.iter().map(|missing| finding(name, missing)))
+            .collect()
+    }
+    /// The undocumented elements whose name is not in `baseline`, with their file
+    pub fn new_missing(&self, baseline: &HashSet<String>) -> Vec<(&str, &Missing)> {
+        self.files
+            .iter()
+            .flat_map(|(name, file)| {
+                file.missing
+                    .iter()
+                    .map(move |missing| (name.as_str(), missing))
+            })
+            .filter(|(_, missing)| !baseline.contains(&missing.name))
+            .collect()
+    }
+    /// The coverage of all the files, in percent
+    pub fn percent(&self) -> f64 {
+        let total = self.files.values().map(|file| file.total).sum();
+        let missing = self.files.values().map(|file| file.missing.len()).sum();
+        percent(total, missing)
+    }
+    /// The coverage by package and by file, listing the undocumented elements
+    pub fn report(&self) -> String {
+        let mut packages: BTreeMap<&str, Vec<(&String, &FileCoverage)>> = BTreeMap::new();
+        for (name, file) in &self.files {
+            packages.entry(&file.package).or_default().push((name, file));
+        }
+        let mut report = String::new();
+        let mut line = |indent: usize, name: &str, total: usize, missing: usize| {
+            report.push_str(&format!(
+                "{}{}: {}/{} documented ({:.1}%)\n",
+                "  ".repeat(indent),
+                name,
+                total - missing,
+                total,
+                percent(total, missing)
+            ));
+        };
+        for (package, files) in &packages {
+            let total = files.iter().map(|(_, file)| file.total).sum();
+            let missing = files.iter().map(|(_, file)| file.missing.len()).sum();
+            let package = if package.is_empty() { "(default package)" } else { package };
+            line(0, package, total, missing);
+            for (name, file) in files {
+                line(1, name, file.total, file.missing.len());
+            }
+        }
+        let total = self.files.values().map(|file| file.total).sum();
+        let missing = self.files.values().map(|file| file.missing.len()).sum();
+        line(0, "total", total, missing);
+        for (name, file) in &self.files {
+            for missing in &file.missing {
+                report.push_str(&format!(
+                    "{}{}: {} {} is not documented\n",
+                    name,
+                    line_suffix(missing),
+                    missing.kind,
+                    missing.name
+                ));
+            }
+        }
+        report
+    }
+}
+
+/// The percentage of documented elements, 100 when there is none
+fn percent(total: usize, missing: usize) -> f64 {
+    if total == 0 {
+        return 100.0;
+    }
+    (total - missing) as f64 * 100.0 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::tests::fixtures::compile;
+
+    fn fixture_coverage(names: &[&str]) -> Coverage {
+        let mut coverage = Coverage::default();
+        for fixture in names {
+            let fd = compile(fixture).descriptor_pool().get_file_by_name(fixture).unwrap();
+            coverage.add(&fd, &DescriptionSelector::default()).unwrap();
+        }
+        coverage
+    }
+    #[test]
+    fn test_coverage() {
+        // documented by comments, and by options
+        assert_eq!(fixture_coverage(&["basic.expected.proto"]).percent(), 100.0);
+        assert_eq!(fixture_coverage(&["basic.proto"]).percent(), 100.0);
+        let coverage = fixture_coverage(&["sidecar.proto", "other.proto"]);
+        assert_eq!(
+            coverage.report(),
+            r#"other: 2/3 documented (66.7%)
+  other.proto: 2/3 documented (66.7%)
+test: 1/4 documented (25.0%)
+  sidecar.proto: 1/4 documented (25.0%)
+total: 3/7 documented (42.9%)
+other.proto:6: enum value other.COLOR_UNSPECIFIED is not documented
+sidecar.proto:5: message test.MyMessage is not documented
+sidecar.proto:10: enum test.MyEnum is not documented
+sidecar.proto:11: enum value test.MY_ENUM_VALUE is not documented
+"#
+        );
+    }
+    #[test]
+    fn test_synthetic_elements() {
+        // map entries and the oneofs of proto3 `optional` fields cannot be documented
+        let coverage = fixture_coverage(&["site.proto"]);
+        let file = &coverage.files["site.proto"];
+        assert_eq!((file.total, file.missing.len()), (24, 10));
+        assert!(file
+            .missing
+            .iter()
+            .all(|missing| missing.span.is_some() && !missing.name.contains("Entry")));
+    }
+    #[test]
+    fn test_baseline() {
+        let coverage = fixture_coverage(&["sidecar.proto"]);
+        let baseline = |fixture: &str| names(&compile(fixture).descriptor_pool());
+        assert!(coverage.new_missing(&baseline("basic.proto")).is_empty());
+        let new: Vec<&str> = coverage
+            .new_missing(&baseline("plain.proto"))
+            .iter()
+            .map(|(_, missing)| missing.name.as_str())
+            .collect();
+        assert_eq!(new, vec!["test.MyEnum", "test.MY_ENUM_VALUE"]);
+        // the entry of a new map field is not a new element
+        let coverage = fixture_coverage(&["site.proto"]);
+        let new: Vec<&str> = coverage
+            .new_missing(&baseline("other.proto"))
+            .iter()
+            .map(|(_, missing)| missing.name.as_str())
+            .collect();
+        assert!(new.contains(&"site.Thing.colors"));
+        assert_eq!(new.len(), 10);
+        let names = baseline("site.proto");
+        assert!(names.contains("site.Thing.colors"));
+        assert!(!names.contains("site.Thing.ColorsEntry"));
+        assert!(!names.contains("site.Thing._stock"));
+        assert!(coverage.new_missing(&names).is_empty());
+    }
+    #[test]
+    fn test_findings() {
+        let findings = fixture_coverage(&["other.proto"]).findings();
+        assert_eq!(
+            findings,
+            vec![Finding {
+                rule: "undocumented",
+                file: "other.proto".to_string(),
+                span: Some(Span {
+                    start_line: 6,
+                    start_column: 5,
+                    end_line: 6,
+                    end_column: 27,
+                }),
+                name: "other.COLOR_UNSPECIFIED".to_string(),
+                message: "enum value other.COLOR_UNSPECIFIED is not documented".to_string(),
+            }]
+        );
+    }
+}
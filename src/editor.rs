@@ -82,7 +82,8 @@ impl Editor{
                     last_position = i.position;
                 }
                 Edition::Delete(d) => {
-                    last_position = d.position + d.length;
+                    // deletions may overlap
+                    last_position = last_position.max(d.position + d.length);
                 }
             }
         }
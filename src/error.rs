@@ -0,0 +1,197 @@
+//! Errors of the library entry points
+
+use std::{path::PathBuf, sync::Arc};
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum Error {
+    #[error("cannot decode the file descriptor set")]
+    #[diagnostic(code(protox_doc::decode))]
+    Decode(#[source] protobuf::Error),
+    #[error("cannot encode the file descriptor set")]
+    #[diagnostic(code(protox_doc::encode))]
+    Encode(#[source] protobuf::Error),
+    #[error("invalid file descriptor set")]
+    #[diagnostic(code(protox_doc::invalid_descriptors))]
+    InvalidDescriptors(#[source] prost_reflect::DescriptorError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Compile(#[from] protox::Error),
+    #[error("invalid file pattern {pattern}")]
+    #[diagnostic(code(protox_doc::pattern))]
+    Pattern {
+        pattern: String,
+        #[source]
+        source: glob::PatternError,
+    },
+    #[error("cannot access {}", path.display())]
+    #[diagnostic(code(protox_doc::io))]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{} is not in the include directory {}", file.display(), include.display())]
+    #[diagnostic(
+        code(protox_doc::not_included),
+        help("the first include directory must contain the files to convert")
+    )]
+    NotIncluded { file: PathBuf, include: PathBuf },
+    #[error("{name} is missing from the compiled files")]
+    #[diagnostic(code(protox_doc::missing_file))]
+    MissingFile { name: String },
+    #[error("the description of {name} is not text")]
+    #[diagnostic(
+        code(protox_doc::not_text),
+        help("description options must be strings, UTF-8 bytes or messages of those, possibly repeated")
+    )]
+    NotText {
+        name: String,
+        #[source_code]
+        src: Option<Arc<NamedSource<String>>>,
+        #[label("this description")]
+        span: Option<SourceSpan>,
+    },
+    #[error("{name} has several description options: {}", candidates.join(", "))]
+    #[diagnostic(
+        code(protox_doc::ambiguous),
+        help(
+            "select the description extension with --description-extension or the --*-id options"
+        )
+    )]
+    Ambiguous {
+        name: String,
+        candidates: Vec<String>,
+        #[source_code]
+        src: Option<Arc<NamedSource<String>>>,
+        #[label("this element")]
+        span: Option<SourceSpan>,
+    },
+    #[error("{name} has both a comment and a different description option")]
+    #[diagnostic(
+        code(protox_doc::merge_conflict),
+        help("choose how to merge them with --merge")
+    )]
+    MergeConflict {
+        name: String,
+        #[source_code]
+        src: Option<Arc<NamedSource<String>>>,
+        #[label("this element")]
+        span: Option<SourceSpan>,
+    },
+    #[error("{file}:{line}: {name} already has a different description option {id}")]
+    #[diagnostic(
+        code(protox_doc::existing_description),
+        help("choose what to do with existing values with --existing")
+    )]
+    ExistingDescription {
+        name: String,
+        id: u32,
+        file: String,
+        line: usize,
+    },
+    #[error("the doc message {name} is not in the descriptor set")]
+    #[diagnostic(
+        code(protox_doc::unknown_doc_message),
+        help("the descriptor set must include the file defining it")
+    )]
+    UnknownDocMessage { name: String },
+    #[error("the {options} description option is not declared, and --embed-doc-proto only declares strings, not {message}")]
+    #[diagnostic(
+        code(protox_doc::embed_structured),
+        help("declare the extension with the doc message type in the descriptor set")
+    )]
+    EmbedStructured { message: String, options: String },
+    #[error("{file}:{line}: the doc message {message} has no `{field}` field")]
+    #[diagnostic(
+        code(protox_doc::missing_doc_field),
+        help("add the field to the doc message, or remove the tag from the comment")
+    )]
+    MissingDocField {
+        message: String,
+        field: &'static str,
+        file: String,
+        line: usize,
+    },
+    #[error("{file}:{line}: the `{field}` field of the doc message {message} cannot hold {part}")]
+    #[diagnostic(
+        code(protox_doc::doc_field_type),
+        help("make it a `string` field, or remove the tag from the comment")
+    )]
+    DocFieldType {
+        message: String,
+        field: String,
+        part: &'static str,
+        file: String,
+        line: usize,
+    },
+    #[error("no description option id is given")]
+    #[diagnostic(
+        code(protox_doc::no_ids),
+        help("give the ids with --message-id, --field-id, ...")
+    )]
+    NoDescriptionIds,
+    #[error("cannot parse the sidecar file {}: {message}", path.display())]
+    #[diagnostic(
+        code(protox_doc::invalid_sidecar),
+        help("it must map full names to doc texts, or to entries with a `doc`, as written by extract")
+    )]
+    InvalidSidecar { path: PathBuf, message: String },
+    #[error("unknown element(s) in {}: {}", path.display(), names.join(", "))]
+    #[diagnostic(
+        code(protox_doc::unknown_names),
+        help("elements go by their full name, without the leading dot, and files by their name")
+    )]
+    UnknownNames { path: PathBuf, names: Vec<String> },
+    #[error("the documentation coverage is {percent:.1}%, below {min}%")]
+    #[diagnostic(code(protox_doc::low_coverage))]
+    LowCoverage { percent: f64, min: f64 },
+    #[error("{count} new element(s) are not documented")]
+    #[diagnostic(
+        code(protox_doc::new_undocumented),
+        help("elements missing from the baseline must be documented")
+    )]
+    NewUndocumented { count: usize },
+    #[error("{count} documentation finding(s)")]
+    #[diagnostic(
+        code(protox_doc::lint),
+        help("fix the documentation, or ignore a rule with a `// protox-doc:ignore <rule>` comment")
+    )]
+    LintFindings { count: usize },
+    #[error("{count} description(s) drifted in the round trip")]
+    #[diagnostic(
+        code(protox_doc::drifted),
+        help("the descriptions would not survive the migration to comments as they are")
+    )]
+    Drifted { count: usize },
+    #[error("the round trip changed {} outside of the descriptions", files.join(", "))]
+    #[diagnostic(code(protox_doc::roundtrip_mismatch))]
+    RoundtripMismatch { files: Vec<String> },
+    #[error("{count} file(s) still carry description options")]
+    #[diagnostic(code(protox_doc::pending))]
+    Pending { count: usize },
+}
+
+impl Error {
+    pub(crate) fn io(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Io { path, source }
+    }
+    /// Point the error at `span` in the source file `name`, when it comes from the source
+    pub(crate) fn in_source(mut self, name: &str, text: &str, at: SourceSpan) -> Error {
+        match &mut self {
+            Error::NotText { src, span, .. }
+            | Error::Ambiguous { src, span, .. }
+            | Error::MergeConflict { src, span, .. } => {
+                *src = Some(Arc::new(NamedSource::new(name, text.to_string())));
+                *span = Some(at);
+            }
+            _ => {}
+        }
+        self
+    }
+}
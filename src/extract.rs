@@ -0,0 +1,347 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::comments2option::{process_markdown_like_whitespace, strip_comment_markers};
+use crate::error::{Error, Result};
+use crate::option2comments::{
+    describe, description_text, get_description, select_description, DescriptionSelector,
+};
+use crate::path_resolver::{prost::elements, tag};
+use prost_reflect::{prost_types::source_code_info::Location, DescriptorPool, FileDescriptor};
+use protox::Compiler;
+use serde::Serialize;
+
+#[derive(Debug, Default, clap::Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub(crate) input: Input,
+    /// Where to read the documentation from.
+    #[clap(long = "from", value_enum, default_value_t)]
+    pub(crate) from: DocSource,
+    /// The format of the output.
+    #[clap(long = "format", value_enum, default_value_t)]
+    pub(crate) format: Format,
+    /// The path to write the documentation to, instead of the standard output.
+    #[clap(short = 'o', long = "output", value_name = "PATH", value_parser)]
+    pub(crate) output: Option<PathBuf>,
+    #[clap(flatten)]
+    pub(crate) selector: DescriptionSelector,
+}
+
+/// The files to read, either compiled from source or from a FileDescriptorSet
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct Input {
+    /// The source file(s) to compile
+    #[clap(
+        value_name = "PROTO_FILES",
+        required_unless_present = "descriptor_set",
+        value_parser
+    )]
+    pub(crate) files: Vec<PathBuf>,
+    /// The directory in which to search for imports.
+    #[clap(
+        short = 'I',
+        long = "include",
+        visible_alias = "proto_path_glob",
+        value_name = "PATH_GLOB",
+        default_value = ".",
+        value_parser
+    )]
+    pub(crate) includes: Vec<PathBuf>,
+    /// Read an encoded FileDescriptorSet instead of compiling source files.
+    #[clap(long = "descriptor-set", value_name = "PATH", conflicts_with = "files", value_parser)]
+    pub(crate) descriptor_set: Option<PathBuf>,
+}
+
+/// Where the documentation is read from
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum DocSource {
+    /// Leading (or trailing) comments, which needs source info
+    #[default]
+    Comments,
+    /// Description options
+    Options,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Json,
+    Yaml,
+}
+
+/// The documentation of an element
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DocEntry {
+    pub kind: &'static str,
+    pub doc: String,
+    pub file: String,
+    /// The lines of the element in its file, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<LineSpan>,
+}
+
+/// First and last lines of an element, starting at 1
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LineSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The documentation of the elements, by full name, files by their name
+pub type Docs = BTreeMap<String, DocEntry>;
+
+pub fn entry_point(args: Args) -> Result<()> {
+    let (pool, files) = args.input.load()?;
+    let mut docs = Docs::new();
+    for name in files {
+        let fd = pool
+            .get_file_by_name(&name)
+            .ok_or_else(|| Error::MissingFile { name: name.clone() })?;
+        extract(&fd, args.from, &args.selector, &mut docs)?;
+    }
+    let text = match args.format {
+        Format::Json => serde_json::to_string_pretty(&docs).unwrap() + "\n",
+        Format::Yaml => serde_yaml::to_string(&docs).unwrap(),
+    };
+    match &args.output {
+        Some(output) => std::fs::write(output, text).map_err(Error::io(output))?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+impl Input {
+    /// The directory the names of the compiled files are relative to, if any
+    pub(crate) fn source_root(&self) -> Option<&Path> {
+        match &self.descriptor_set {
+            Some(_) => None,
+            None => self.includes.first().map(PathBuf::as_path),
+        }
+    }
+    /// The pool of the input files, and the names of the files to document
+    /// The files of a descriptor set are all documented, except the `google` ones.
+    pub(crate) fn load(&self) -> Result<(DescriptorPool, Vec<String>)> {
+        match &self.descriptor_set {
+            Some(path) => {
+                let bytes = std::fs::read(path).map_err(Error::io(path))?;
+                let pool =
+                    DescriptorPool::decode(bytes.as_slice()).map_err(Error::InvalidDescriptors)?;
+                let files = pool
+                    .files()
+                    .map(|file| file.name().to_string())
+                    .filter(|name| !name.starts_with("google/"))
+                    .collect();
+                Ok((pool, files))
+            }
+            None => self.compile(),
+        }
+    }
+    /// Compile the source files, returns the pool and the names of the files
+    fn compile(&self) -> Result<(DescriptorPool, Vec<String>)> {
+        let mut compiler = Compiler::new(&self.includes)?;
+        compiler.include_imports(true);
+        compiler.include_source_info(true);
+        for file_glob in &self.files {
+            let file_glob = file_glob.to_string_lossy();
+            let file_glob = shellexpand::tilde(&file_glob);
+            let files = glob::glob(&file_glob).map_err(|source| Error::Pattern {
+                pattern: file_glob.to_string(),
+                source,
+            })?;
+            for file in files {
+                let file = file.map_err(|err| Error::Io {
+                    path: err.path().to_path_buf(),
+                    source: err.into_error(),
+                })?;
+                compiler.open_file(file)?;
+            }
+        }
+        let files = compiler
+            .files()
+            .filter(|file| !file.is_import())
+            .map(|file| file.name().to_string())
+            .collect();
+        Ok((compiler.descriptor_pool(), files))
+    }
+}
+
+/// Add the documentation of the elements of `fd` to `docs`
+pub fn extract(
+    fd: &FileDescriptor,
+    from: DocSource,
+    selector: &DescriptionSelector,
+    docs: &mut Docs,
+) -> Result<()> {
+    let source_info = fd.file_descriptor_proto().source_code_info.as_ref();
+    let location = |path: &[i32]| {
+        source_info.and_then(|info| info.location.iter().find(|loc| loc.path == path))
+    };
+    let file_doc = match from {
+        DocSource::Comments => file_comments(source_info.map_or(&[], |info| &info.location)),
+        DocSource::Options => {
+            match select_description(fd.name(), &fd.options(), selector, selector.ids.file)? {
+                Some(ext) => Some(description_text(&ext, fd.name())?),
+                None => None,
+            }
+        }
+    };
+    if let Some(doc) = file_doc {
+        docs.insert(
+            fd.name().to_string(),
+            DocEntry {
+                kind: "file",
+                doc,
+                file: fd.name().to_string(),
+                span: None,
+            },
+        );
+    }
+    for (path, pathed) in elements(fd) {
+        let (kind, name) = describe(&pathed);
+        let loc = location(&path);
+        let doc = match from {
+            DocSource::Comments => loc.and_then(comments),
+            DocSource::Options => match get_description(&pathed, selector)? {
+                Some(ext) => Some(description_text(&ext, &name)?),
+                None => None,
+            },
+        };
+        let Some(doc) = doc else {
+            continue;
+        };
+        docs.insert(
+            name,
+            DocEntry {
+                kind,
+                doc,
+                file: fd.name().to_string(),
+                span: loc.map(line_span),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// The documentation of an element from its leading comments, or its trailing ones
+pub(crate) fn comments(loc: &Location) -> Option<String> {
+    let comments = loc
+        .leading_comments
+        .as_deref()
+        .or(loc.trailing_comments.as_deref())?;
+    let doc = process_markdown_like_whitespace(&strip_comment_markers(comments));
+    Some(doc.trim().to_string())
+}
+
+/// The documentation of a file, from the comments above its `syntax` or `package` statement
+fn file_comments(locations: &[Location]) -> Option<String> {
+    let loc = locations.iter().find(|loc| {
+        (loc.path == [tag::file::SYNTAX] || loc.path == [tag::file::PACKAGE])
+            && (loc.leading_comments.is_some() || !loc.leading_detached_comments.is_empty())
+    })?;
+    let mut comments = loc.leading_detached_comments.clone();
+    comments.extend(loc.leading_comments.clone());
+    let comments = comments
+        .iter()
+        .map(|comment| strip_comment_markers(comment))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(process_markdown_like_whitespace(&comments).trim().to_string())
+}
+
+fn line_span(loc: &Location) -> LineSpan {
+    let start = loc.span[0] as usize + 1;
+    let end = if loc.span.len() == 4 { loc.span[2] as usize + 1 } else { start };
+    LineSpan { start, end }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::compile;
+    use pretty_assertions::assert_eq;
+
+    fn extract_fixture(fixture: &str, from: DocSource) -> Docs {
+        let fd = compile(fixture).descriptor_pool().get_file_by_name(fixture).unwrap();
+        let mut docs = Docs::new();
+        extract(&fd, from, &DescriptionSelector::default(), &mut docs).unwrap();
+        docs
+    }
+    #[test]
+    fn test_extract_comments_and_options() {
+        let from_comments = extract_fixture("file.expected.proto", DocSource::Comments);
+        let from_options = extract_fixture("file.proto", DocSource::Options);
+        let docs = |docs: &Docs| {
+            docs.iter()
+                .map(|(name, entry)| (name.clone(), entry.kind, entry.doc.clone()))
+                .filter(|(name, _, _)| !name.ends_with(".proto"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(docs(&from_comments), docs(&from_options));
+        assert_eq!(
+            from_comments["file.expected.proto"].doc,
+            from_options["file.proto"].doc
+        );
+    }
+    #[test]
+    fn test_extract_json() {
+        let docs = extract_fixture("basic.expected.proto", DocSource::Comments);
+        let json = serde_json::to_value(&docs).unwrap();
+        assert_eq!(
+            json["test.MyMessage.my_field"],
+            serde_json::json!({
+                "kind": "field",
+                "doc": "This is a field documentation.",
+                "file": "basic.expected.proto",
+                "span": { "start": 8, "end": 8 },
+            })
+        );
+        assert_eq!(json["test.MyEnum"]["kind"], "enum");
+    }
+    #[test]
+    fn test_extract_undeclared_ids() {
+        use crate::comments2option::{comments2option, Config, DescriptionIds};
+        // comments2option without `--embed-doc-proto` does not declare the extensions
+        let ids = DescriptionIds {
+            message: Some(5000),
+            field: Some(5000),
+            ..Default::default()
+        };
+        let compiler = compile("plain.proto");
+        let config = Config {
+            ids: ids.clone(),
+            ..Default::default()
+        };
+        let res = comments2option(&compiler.encode_file_descriptor_set(), &config).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("protox-doc-extract-{}.pb", std::process::id()));
+        std::fs::write(&path, res).unwrap();
+        let input = Input {
+            descriptor_set: Some(path.clone()),
+            ..Default::default()
+        };
+        let (pool, files) = input.load().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(files, vec!["plain.proto"]);
+        let selector = DescriptionSelector {
+            names: Vec::new(),
+            ids,
+        };
+        let mut docs = Docs::new();
+        let fd = pool.get_file_by_name("plain.proto").unwrap();
+        extract(&fd, DocSource::Options, &selector, &mut docs).unwrap();
+        let docs: Vec<(&str, &str)> = docs
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry.doc.as_str()))
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                ("test.MyMessage", "This is a message documentation."),
+                ("test.MyMessage.my_field", "This is a field documentation."),
+            ]
+        );
+    }
+}
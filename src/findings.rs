@@ -0,0 +1,311 @@
+//! Findings of the documentation checks, printed as text, as SARIF 2.1 or as JSON lines.
+
+use std::path::Path;
+
+use prost_reflect::prost_types::source_code_info::Location;
+use serde::Serialize;
+
+/// The format of the findings
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// `file:line:column: rule: message (element)`
+    #[default]
+    Text,
+    /// A SARIF 2.1.0 log, e.g. for code review tools
+    Sarif,
+    /// A JSON object per line
+    Jsonl,
+}
+
+/// The span of an element in its file, starting at 1, the end column being exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Span {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    /// The span of a source location: `[start line, start column, (end line,) end column]`
+    pub fn new(loc: &Location) -> Option<Span> {
+        let span: Vec<usize> = loc.span.iter().map(|&i| i as usize + 1).collect();
+        match span[..] {
+            [start_line, start_column, end_column] => Some(Span {
+                start_line,
+                start_column,
+                end_line: start_line,
+                end_column,
+            }),
+            [start_line, start_column, end_line, end_column] => Some(Span {
+                start_line,
+                start_column,
+                end_line,
+                end_column,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// An element breaking a rule
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Finding {
+    /// The id of the rule, e.g. `capital-start`
+    pub rule: &'static str,
+    pub file: String,
+    /// The span of the element, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+    /// The full name of the element
+    pub name: String,
+    pub message: String,
+}
+
+/// A rule of a tool, with a short description
+pub struct RuleInfo {
+    pub id: &'static str,
+    pub description: String,
+}
+
+/// The findings in `format`, `rules` being all the rules that could have been broken
+///
+/// The files of the findings are relative to `root`, e.g. the include directory, when known.
+/// In SARIF, they are relative to the `SRCROOT` base, which is `root` as a `file` URI.
+pub fn render(
+    findings: &[Finding],
+    format: Format,
+    rules: &[RuleInfo],
+    root: Option<&Path>,
+) -> String {
+    match format {
+        Format::Text => findings
+            .iter()
+            .map(|finding| {
+                let position = finding
+                    .span
+                    .map(|span| format!(":{}:{}", span.start_line, span.start_column))
+                    .unwrap_or_default();
+                format!(
+                    "{}{}: {}: {} ({})\n",
+                    finding.file, position, finding.rule, finding.message, finding.name
+                )
+            })
+            .collect(),
+        Format::Sarif => {
+            serde_json::to_string_pretty(&sarif(findings, rules, root)).unwrap() + "\n"
+        }
+        Format::Jsonl => findings
+            .iter()
+            .map(|finding| serde_json::to_string(finding).unwrap() + "\n")
+            .collect(),
+    }
+}
+
+/// The base of the files of the findings in SARIF
+const SRCROOT: &str = "SRCROOT";
+
+/// A SARIF log with a run of protox-doc
+fn sarif(findings: &[Finding], rules: &[RuleInfo], root: Option<&Path>) -> serde_json::Value {
+    let results: Vec<serde_json::Value> = findings
+        .iter()
+        .map(|finding| {
+            let mut physical = serde_json::json!({
+                "artifactLocation": { "uri": finding.file, "uriBaseId": SRCROOT },
+            });
+            if let Some(span) = finding.span {
+                physical["region"] = serde_json::json!({
+                    "startLine": span.start_line,
+                    "startColumn": span.start_column,
+                    "endLine": span.end_line,
+                    "endColumn": span.end_column,
+                });
+            }
+            let mut result = serde_json::json!({
+                "ruleId": finding.rule,
+                "level": "warning",
+                "message": { "text": finding.message },
+                "locations": [{
+                    "physicalLocation": physical,
+                    "logicalLocations": [{ "fullyQualifiedName": finding.name }],
+                }],
+            });
+            if let Some(index) = rules.iter().position(|rule| rule.id == finding.rule) {
+                result["ruleIndex"] = index.into();
+            }
+            result
+        })
+        .collect();
+    let rules: Vec<serde_json::Value> = rules
+        .iter()
+        .map(|rule| {
+            serde_json::json!({
+                "id": rule.id,
+                "shortDescription": { "text": rule.description },
+            })
+        })
+        .collect();
+    let mut run = serde_json::json!({
+        "tool": {
+            "driver": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "rules": rules,
+            },
+        },
+        "results": results,
+    });
+    if let Some(uri) = root.and_then(directory_uri) {
+        run["originalUriBaseIds"] = serde_json::json!({ SRCROOT: { "uri": uri } });
+    }
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [run],
+    })
+}
+
+/// The absolute `file` URI of the directory `dir`, ending with a slash
+fn directory_uri(dir: &Path) -> Option<String> {
+    let dir = dir.canonicalize().ok()?;
+    let path = dir.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        // a Windows drive
+        uri.push('/');
+    }
+    for c in path.trim_end_matches('/').chars() {
+        match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '.' | '_' | '~' | '/' | ':' => uri.push(c),
+            _ => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    uri.push_str(&format!("%{:02X}", byte));
+                }
+            }
+        }
+    }
+    uri.push('/');
+    Some(uri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn findings() -> Vec<Finding> {
+        vec![
+            Finding {
+                rule: "capital-start",
+                file: "test.proto".to_string(),
+                span: Some(Span {
+                    start_line: 5,
+                    start_column: 1,
+                    end_line: 7,
+                    end_column: 2,
+                }),
+                name: "test.Thing".to_string(),
+                message: "the documentation should start with a capital letter".to_string(),
+            },
+            Finding {
+                rule: "no-todo",
+                file: "test.proto".to_string(),
+                span: None,
+                name: "test.Other".to_string(),
+                message: "the documentation contains TODO".to_string(),
+            },
+        ]
+    }
+    fn rules() -> Vec<RuleInfo> {
+        ["capital-start", "no-todo"]
+            .into_iter()
+            .map(|id| RuleInfo {
+                id,
+                description: format!("The {} rule", id),
+            })
+            .collect()
+    }
+    #[test]
+    fn test_span() {
+        let loc = |span: Vec<i32>| Location {
+            span,
+            ..Default::default()
+        };
+        assert_eq!(
+            Span::new(&loc(vec![4, 0, 12])),
+            Some(Span {
+                start_line: 5,
+                start_column: 1,
+                end_line: 5,
+                end_column: 13
+            })
+        );
+        assert_eq!(Span::new(&loc(vec![4, 0, 6, 1])).unwrap().end_line, 7);
+        assert_eq!(Span::new(&loc(vec![])), None);
+    }
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            render(&findings(), Format::Text, &rules(), None),
+            r#"test.proto:5:1: capital-start: the documentation should start with a capital letter (test.Thing)
+test.proto: no-todo: the documentation contains TODO (test.Other)
+"#
+        );
+    }
+    #[test]
+    fn test_jsonl() {
+        assert_eq!(
+            render(&findings(), Format::Jsonl, &rules(), None),
+            r#"{"rule":"capital-start","file":"test.proto","span":{"start_line":5,"start_column":1,"end_line":7,"end_column":2},"name":"test.Thing","message":"the documentation should start with a capital letter"}
+{"rule":"no-todo","file":"test.proto","name":"test.Other","message":"the documentation contains TODO"}
+"#
+        );
+    }
+    #[test]
+    fn test_sarif() {
+        let sarif = sarif(&findings(), &rules(), None);
+        let run = &sarif["runs"][0];
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["name"], "protox-doc");
+        assert_eq!(run["tool"]["driver"]["rules"][1]["id"], "no-todo");
+        assert_eq!(
+            run["results"][0],
+            serde_json::json!({
+                "ruleId": "capital-start",
+                "ruleIndex": 0,
+                "level": "warning",
+                "message": { "text": "the documentation should start with a capital letter" },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "test.proto", "uriBaseId": "SRCROOT" },
+                        "region": {
+                            "startLine": 5,
+                            "startColumn": 1,
+                            "endLine": 7,
+                            "endColumn": 2,
+                        },
+                    },
+                    "logicalLocations": [{ "fullyQualifiedName": "test.Thing" }],
+                }],
+            })
+        );
+        assert!(run["results"][1]["locations"][0]["physicalLocation"]
+            .get("region")
+            .is_none());
+    }
+    #[test]
+    fn test_sarif_root() {
+        assert!(sarif(&findings(), &rules(), None)["runs"][0]
+            .get("originalUriBaseIds")
+            .is_none());
+        let root = std::env::temp_dir().join("protox doc");
+        std::fs::create_dir_all(&root).unwrap();
+        let sarif = sarif(&findings(), &rules(), Some(&root));
+        let uri = sarif["runs"][0]["originalUriBaseIds"]["SRCROOT"]["uri"]
+            .as_str()
+            .unwrap();
+        assert!(uri.starts_with("file:///"), "{}", uri);
+        assert!(uri.ends_with("/protox%20doc/"), "{}", uri);
+    }
+}
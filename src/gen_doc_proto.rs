@@ -0,0 +1,185 @@
+use std::{fmt::Write as _, path::PathBuf};
+
+use crate::comments2option::{DescriptionIds, DOC_PROTO};
+use crate::error::{Error, Result};
+use protox::{
+    file::{ChainFileResolver, File, FileResolver, GoogleFileResolver},
+    Compiler,
+};
+
+#[derive(Debug, Default, clap::Parser)]
+pub struct Args {
+    /// The package of the generated file.
+    #[clap(long = "package", default_value = "protox_doc")]
+    pub(crate) package: String,
+    /// The name of the generated file, as imported by other files.
+    /// It is namespaced by default, so that it does not collide with a `doc.proto` of the
+    /// include tree, and matches the file added by `comments2option --embed-doc-proto`.
+    #[clap(long = "name", value_name = "FILE_NAME", default_value = DOC_PROTO)]
+    pub(crate) name: String,
+    /// The path to write the generated `.proto` file to, instead of the standard output.
+    #[clap(short = 'o', long = "output", value_name = "PATH", value_parser)]
+    pub(crate) output: Option<PathBuf>,
+    /// Also write the extensions as an encoded FileDescriptorSet holding the generated file.
+    /// It can be appended to another descriptor set by concatenating both files.
+    #[clap(long = "descriptor-set-out", value_name = "PATH", value_parser)]
+    pub(crate) descriptor_set_out: Option<PathBuf>,
+    #[clap(flatten)]
+    pub(crate) ids: DescriptionIds,
+}
+
+pub fn entry_point(args: Args) -> Result<()> {
+    let source = doc_proto(&args.ids, &args.package)?;
+    match &args.output {
+        Some(output) => std::fs::write(output, &source).map_err(Error::io(output))?,
+        None => print!("{}", source),
+    }
+    if let Some(out) = &args.descriptor_set_out {
+        let set = doc_descriptor_set(&args.ids, &args.package, &args.name, false)?;
+        std::fs::write(out, set).map_err(Error::io(out))?;
+    }
+    Ok(())
+}
+
+/// The description extensions configured by `ids`, as (options message, extension name, number)
+fn extensions(ids: &DescriptionIds) -> Vec<(&'static str, &'static str, u32)> {
+    let extensions = [
+        ("FileOptions", "file_description", ids.file),
+        ("MessageOptions", "message_description", ids.message),
+        ("FieldOptions", "field_description", ids.field),
+        ("OneofOptions", "oneof_description", ids.oneof),
+        ("EnumOptions", "enum_description", ids.enum_),
+        ("EnumValueOptions", "enum_value_description", ids.enum_value),
+        ("ServiceOptions", "service_description", ids.service),
+        ("MethodOptions", "method_description", ids.method),
+        // extensions are fields, they share the field description when the ids are the same
+        ("FieldOptions", "extension_description", ids.extension.filter(|id| Some(*id) != ids.field)),
+    ];
+    extensions
+        .into_iter()
+        .filter_map(|(options, name, id)| Some((options, name, id?)))
+        .collect()
+}
+
+/// The source of a `.proto` file defining the description extensions of `ids`
+pub fn doc_proto(ids: &DescriptionIds, package: &str) -> Result<String> {
+    if ids.is_empty() {
+        return Err(Error::NoDescriptionIds);
+    }
+    let mut source = String::new();
+    source.push_str("// Generated by gen-doc-proto, do not edit.\n");
+    source.push_str("syntax = \"proto3\";\n");
+    writeln!(source, "package {};", package).unwrap();
+    source.push_str("import \"google/protobuf/descriptor.proto\";\n");
+    let extensions = extensions(ids);
+    let mut extendees: Vec<&str> = Vec::new();
+    for (options, _, _) in &extensions {
+        if !extendees.contains(options) {
+            extendees.push(options);
+        }
+    }
+    for extendee in extendees {
+        writeln!(source, "\nextend google.protobuf.{} {{", extendee).unwrap();
+        for (_, name, id) in extensions.iter().filter(|(options, _, _)| *options == extendee) {
+            writeln!(source, "    string {} = {};", name, id).unwrap();
+        }
+        source.push_str("}\n");
+    }
+    Ok(source)
+}
+
+/// The description extensions of `ids`, compiled as the file `name`, in an encoded FileDescriptorSet
+/// With `include_imports`, `google/protobuf/descriptor.proto` is part of the set as well.
+pub fn doc_descriptor_set(
+    ids: &DescriptionIds,
+    package: &str,
+    name: &str,
+    include_imports: bool,
+) -> Result<Vec<u8>> {
+    let mut resolver = ChainFileResolver::new();
+    resolver.add(GeneratedFile {
+        name: name.to_string(),
+        source: doc_proto(ids, package)?,
+    });
+    resolver.add(GoogleFileResolver::new());
+    let mut compiler = Compiler::with_file_resolver(resolver);
+    compiler.include_imports(include_imports);
+    compiler.open_file(name)?;
+    Ok(compiler.encode_file_descriptor_set())
+}
+
+/// Resolves a single file from its source
+struct GeneratedFile {
+    name: String,
+    source: String,
+}
+impl FileResolver for GeneratedFile {
+    fn open_file(&self, name: &str) -> std::result::Result<File, protox::Error> {
+        if name == self.name {
+            File::from_source(name, &self.source)
+        } else {
+            Err(protox::Error::file_not_found(name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use prost_reflect::DescriptorPool;
+    use crate::tests::fixtures::test_ids;
+
+    #[test]
+    fn test_doc_proto() {
+        let ids = DescriptionIds {
+            message: Some(1000),
+            field: Some(1001),
+            extension: Some(1001),
+            ..Default::default()
+        };
+        assert_eq!(
+            doc_proto(&ids, "my.doc").unwrap(),
+            r#"// Generated by gen-doc-proto, do not edit.
+syntax = "proto3";
+package my.doc;
+import "google/protobuf/descriptor.proto";
+
+extend google.protobuf.MessageOptions {
+    string message_description = 1000;
+}
+
+extend google.protobuf.FieldOptions {
+    string field_description = 1001;
+}
+"#
+        );
+        let err = doc_proto(&DescriptionIds::default(), "doc").unwrap_err();
+        assert!(matches!(err, Error::NoDescriptionIds));
+    }
+    #[test]
+    fn test_doc_descriptor_set() {
+        // fields and extensions both extend `FieldOptions`
+        let ids = DescriptionIds {
+            extension: Some(1001),
+            ..test_ids()
+        };
+        let set = doc_descriptor_set(&ids, "doc", "protox_doc/doc.proto", true).unwrap();
+        let pool = DescriptorPool::decode(set.as_slice()).unwrap();
+        assert!(pool.get_file_by_name("protox_doc/doc.proto").is_some());
+        let extensions: Vec<(String, u32)> = pool
+            .all_extensions()
+            .filter(|ext| ext.package_name() == "doc")
+            .map(|ext| (ext.containing_message().name().to_string(), ext.number()))
+            .collect();
+        assert_eq!(extensions.len(), 9);
+        assert!(extensions.contains(&("FieldOptions".to_string(), 1001)));
+    }
+    #[test]
+    fn test_default_name() {
+        use clap::Parser;
+        let args = Args::parse_from(["gen-doc-proto", "--message-id", "1000"]);
+        assert_eq!(args.name, DOC_PROTO);
+        assert_eq!(args.package, "protox_doc");
+    }
+}
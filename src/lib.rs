@@ -1,8 +1,20 @@
 pub(crate)mod editor;
+pub(crate)mod markdown;
 pub(crate)mod path_resolver;
+pub(crate)mod references;
+pub(crate)mod tags;
 
 pub mod comments2option;
+pub mod coverage;
+pub mod error;
+pub mod extract;
+pub mod findings;
+pub mod gen_doc_proto;
+pub mod lint;
 pub mod option2comments;
+pub mod render;
+pub mod roundtrip;
+pub mod sidecar;
 
 #[cfg(test)]
 pub mod tests;
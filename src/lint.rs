@@ -0,0 +1,399 @@
+//! Style rules for documentation text.
+//!
+//! A rule can be ignored for an element with a `// protox-doc:ignore <rule>` comment
+//! next to it; without a rule name, all the rules are ignored.
+
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use prost_reflect::{prost_types::source_code_info::Location, FileDescriptor};
+use regex::Regex;
+
+use crate::error::{Error, Result};
+use crate::extract::{self, DocSource, Input};
+use crate::findings::{render, Finding, Format, RuleInfo, Span};
+use crate::markdown::{self, Block};
+use crate::option2comments::{describe, description_text, get_description, DescriptionSelector};
+use crate::path_resolver::prost::elements;
+use crate::references::{references, Names};
+
+/// The marker of the comments ignoring rules
+const IGNORE_MARKER: &str = "protox-doc:ignore";
+
+#[derive(Debug, Default, clap::Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub(crate) input: Input,
+    /// Where to read the documentation from.
+    #[clap(long = "from", value_enum, default_value_t)]
+    pub(crate) from: DocSource,
+    #[clap(flatten)]
+    pub(crate) rules: Rules,
+    /// The format of the findings.
+    #[clap(long = "format", value_enum, default_value_t)]
+    pub(crate) format: Format,
+    #[clap(flatten)]
+    pub(crate) selector: DescriptionSelector,
+}
+
+/// A style rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Rule {
+    /// The documentation starts with a capital letter
+    CapitalStart,
+    /// The documentation ends with a punctuation mark, unless it ends with a list or code
+    EndPunctuation,
+    /// The first sentence is shorter than `--max-summary-length`
+    SummaryLength,
+    /// The documentation contains no `TODO` nor `FIXME`
+    NoTodo,
+    /// The documentation does not merely repeat the name of the element
+    NameRepeat,
+    /// Field documentation does not start with "The field"
+    FieldPrefix,
+    /// References to other elements, as `[foo.v1.Bar]` or `` `Bar.baz` ``, resolve
+    DanglingReference,
+}
+
+const RULES: [Rule; 7] = [
+    Rule::CapitalStart,
+    Rule::EndPunctuation,
+    Rule::SummaryLength,
+    Rule::NoTodo,
+    Rule::NameRepeat,
+    Rule::FieldPrefix,
+    Rule::DanglingReference,
+];
+
+/// Which rules are checked, and their settings
+#[derive(Debug, Clone, clap::Args)]
+pub struct Rules {
+    /// Only check these rules, instead of all of them.
+    #[clap(long = "enable", value_enum, value_name = "RULE")]
+    pub enable: Vec<Rule>,
+    /// Do not check this rule.
+    #[clap(long = "disable", value_enum, value_name = "RULE")]
+    pub disable: Vec<Rule>,
+    /// The maximum length of the first sentence, in characters.
+    #[clap(long = "max-summary-length", value_name = "CHARS", default_value_t = 100)]
+    pub max_summary_length: usize,
+}
+impl Rules {
+    /// The rules to check
+    fn enabled(&self) -> Vec<Rule> {
+        RULES
+            .into_iter()
+            .filter(|rule| self.enable.is_empty() || self.enable.contains(rule))
+            .filter(|rule| !self.disable.contains(rule))
+            .collect()
+    }
+}
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            enable: Vec::new(),
+            disable: Vec::new(),
+            max_summary_length: 100,
+        }
+    }
+}
+
+pub fn entry_point(args: Args) -> Result<()> {
+    let (pool, files) = args.input.load()?;
+    let names = args
+        .rules
+        .enabled()
+        .contains(&Rule::DanglingReference)
+        .then(|| Names::new(&pool));
+    let mut findings = Vec::new();
+    for name in files {
+        let fd = pool
+            .get_file_by_name(&name)
+            .ok_or_else(|| Error::MissingFile { name: name.clone() })?;
+        findings.extend(lint(&fd, args.from, &args.selector, &args.rules, names.as_ref())?);
+    }
+    let rules: Vec<RuleInfo> = RULES
+        .into_iter()
+        .map(|rule| RuleInfo {
+            id: rule.name(),
+            description: rule.description(),
+        })
+        .collect();
+    let root = args.input.source_root();
+    print!("{}", render(&findings, args.format, &rules, root));
+    if !findings.is_empty() {
+        return Err(Error::LintFindings {
+            count: findings.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Check the documentation of the elements of `fd`
+/// `names` are the names references resolve to, needed by the dangling-reference rule.
+pub(crate) fn lint(
+    fd: &FileDescriptor,
+    from: DocSource,
+    selector: &DescriptionSelector,
+    rules: &Rules,
+    names: Option<&Names>,
+) -> Result<Vec<Finding>> {
+    let source_info = fd.file_descriptor_proto().source_code_info.as_ref();
+    let location = |path: &[i32]| {
+        source_info.and_then(|info| info.location.iter().find(|loc| loc.path == path))
+    };
+    let enabled = rules.enabled();
+    let mut findings = Vec::new();
+    for (path, pathed) in elements(fd) {
+        let (kind, name) = describe(&pathed);
+        let loc = location(&path);
+        let doc = match from {
+            DocSource::Comments => loc.and_then(comments),
+            DocSource::Options => match get_description(&pathed, selector)? {
+                Some(ext) => Some(description_text(&ext, &name)?),
+                None => None,
+            },
+        };
+        let Some(doc) = doc.filter(|doc| !doc.is_empty()) else {
+            continue;
+        };
+        let ignored = loc.map(ignored_rules).unwrap_or_default();
+        let span = loc.and_then(Span::new);
+        let element = Element {
+            kind,
+            name: &name,
+            names,
+        };
+        for rule in &enabled {
+            let ignored = ignored
+                .as_ref()
+                .is_some_and(|ignored| ignored.is_empty() || ignored.contains(rule));
+            if ignored {
+                continue;
+            }
+            if let Some(message) = rule.check(&doc, &element, rules) {
+                findings.push(Finding {
+                    rule: rule.name(),
+                    file: fd.name().to_string(),
+                    span,
+                    name: name.clone(),
+                    message,
+                });
+            }
+        }
+    }
+    // the rules of an element are checked in order, and the sort is stable
+    findings.sort_by_key(|finding| finding.span);
+    Ok(findings)
+}
+
+/// The documentation of an element from its leading comments, or its trailing ones,
+/// without the ignore markers
+fn comments(loc: &Location) -> Option<String> {
+    let without_markers = |comments: &Option<String>| {
+        comments.as_ref().map(|comments| {
+            comments
+                .lines()
+                .filter(|line| !line.contains(IGNORE_MARKER))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    };
+    extract::comments(&Location {
+        leading_comments: without_markers(&loc.leading_comments),
+        trailing_comments: without_markers(&loc.trailing_comments),
+        ..Default::default()
+    })
+}
+
+/// The rules ignored by the comments of an element, empty when they all are,
+/// or `None` when there is no ignore marker
+fn ignored_rules(loc: &Location) -> Option<Vec<Rule>> {
+    let comments = loc
+        .leading_detached_comments
+        .iter()
+        .chain(&loc.leading_comments)
+        .chain(&loc.trailing_comments);
+    let mut ignored: Option<Vec<Rule>> = None;
+    for line in comments.flat_map(|comment| comment.lines()) {
+        let Some((_, rest)) = line.split_once(IGNORE_MARKER) else {
+            continue;
+        };
+        let rules = ignored.get_or_insert_with(Vec::new);
+        let names: Vec<&str> = rest
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|name| !name.is_empty())
+            .collect();
+        if names.is_empty() {
+            // a marker without rule names ignores all of them
+            return Some(Vec::new());
+        }
+        rules.extend(RULES.into_iter().filter(|rule| names.contains(&rule.name())));
+    }
+    ignored
+}
+
+/// The documented element, for the rules
+struct Element<'a> {
+    kind: &'static str,
+    /// The full name of the element
+    name: &'a str,
+    /// The names references can resolve to
+    names: Option<&'a Names>,
+}
+
+impl Rule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::CapitalStart => "capital-start",
+            Rule::EndPunctuation => "end-punctuation",
+            Rule::SummaryLength => "summary-length",
+            Rule::NoTodo => "no-todo",
+            Rule::NameRepeat => "name-repeat",
+            Rule::FieldPrefix => "field-prefix",
+            Rule::DanglingReference => "dangling-reference",
+        }
+    }
+    /// The description of the rule, from its documentation
+    pub fn description(&self) -> String {
+        self.to_possible_value()
+            .and_then(|value| value.get_help().map(ToString::to_string))
+            .unwrap_or_default()
+    }
+    /// What is wrong with `doc`, the documentation of `element`, if anything
+    fn check(&self, doc: &str, element: &Element, rules: &Rules) -> Option<String> {
+        let name = element.name.rsplit('.').next().unwrap_or(element.name);
+        match self {
+            Rule::CapitalStart => doc
+                .starts_with(char::is_lowercase)
+                .then(|| "the documentation should start with a capital letter".to_string()),
+            Rule::EndPunctuation => match markdown::blocks(doc).last() {
+                Some(Block::Paragraph(text)) if !text.ends_with(['.', '!', '?']) => {
+                    Some("the documentation should end with a punctuation mark".to_string())
+                }
+                _ => None,
+            },
+            Rule::SummaryLength => {
+                let length = summary(doc).chars().count();
+                (length > rules.max_summary_length).then(|| {
+                    format!(
+                        "the first sentence is {} characters long, more than {}",
+                        length, rules.max_summary_length
+                    )
+                })
+            }
+            Rule::NoTodo => {
+                static TODO: OnceLock<Regex> = OnceLock::new();
+                let todo = TODO.get_or_init(|| Regex::new(r"\b(TODO|FIXME)\b").unwrap());
+                todo.captures(doc)
+                    .map(|captures| format!("the documentation contains {}", &captures[1]))
+            }
+            Rule::NameRepeat => {
+                let normalize = |text: &str| {
+                    text.chars()
+                        .filter(|c| c.is_alphanumeric())
+                        .flat_map(char::to_lowercase)
+                        .collect::<String>()
+                };
+                (normalize(summary(doc)) == normalize(name))
+                    .then(|| "the documentation only repeats the name of the element".to_string())
+            }
+            Rule::FieldPrefix => (element.kind == "field"
+                && doc.to_lowercase().starts_with("the field"))
+            .then(|| "field documentation should not start with \"The field\"".to_string()),
+            Rule::DanglingReference => {
+                let names = element.names?;
+                let dangling: Vec<String> = references(doc)
+                    .iter()
+                    .filter(|reference| {
+                        names.resolve(reference.name, element.name).is_none()
+                    })
+                    .map(|reference| format!("`{}`", reference.name))
+                    .collect();
+                (!dangling.is_empty())
+                    .then(|| format!("unresolved reference(s): {}", dangling.join(", ")))
+            }
+        }
+    }
+}
+
+/// The first sentence of a documentation
+fn summary(doc: &str) -> &str {
+    let paragraph = doc.lines().next().unwrap_or_default();
+    let end = paragraph
+        .match_indices(['.', '!', '?'])
+        .map(|(i, _)| i + 1)
+        .find(|&i| paragraph[i..].is_empty() || paragraph[i..].starts_with(' '))
+        .unwrap_or(paragraph.len());
+    &paragraph[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::tests::fixtures::compile;
+
+    fn lint_fixture(fixture: &str, rules: &Rules) -> Vec<String> {
+        let pool = compile(fixture).descriptor_pool();
+        let fd = pool.get_file_by_name(fixture).unwrap();
+        let names = Names::new(&pool);
+        let selector = DescriptionSelector::default();
+        lint(&fd, DocSource::Comments, &selector, rules, Some(&names))
+            .unwrap()
+            .iter()
+            .map(|finding| {
+                let span = finding.span.unwrap();
+                format!(
+                    "{}:{}: {} {}",
+                    span.start_line, span.start_column, finding.rule, finding.name
+                )
+            })
+            .collect()
+    }
+    #[test]
+    fn test_lint() {
+        assert_eq!(
+            lint_fixture("lint.proto", &Rules::default()),
+            vec![
+                "5:1: capital-start lint.Thing",
+                "5:1: end-punctuation lint.Thing",
+                "5:1: no-todo lint.Thing",
+                "7:5: field-prefix lint.Thing.name",
+                "9:5: end-punctuation lint.Thing.size",
+                "9:5: name-repeat lint.Thing.size",
+                "11:5: summary-length lint.Thing.weight",
+                "18:5: no-todo lint.Thing.label",
+            ]
+        );
+    }
+    #[test]
+    fn test_enable_disable() {
+        let rules = Rules {
+            enable: vec![Rule::NoTodo, Rule::EndPunctuation],
+            disable: vec![Rule::EndPunctuation],
+            ..Default::default()
+        };
+        assert_eq!(
+            lint_fixture("lint.proto", &rules),
+            vec!["5:1: no-todo lint.Thing", "18:5: no-todo lint.Thing.label"]
+        );
+    }
+    #[test]
+    fn test_dangling_reference() {
+        let rules = Rules {
+            enable: vec![Rule::DanglingReference],
+            ..Default::default()
+        };
+        assert_eq!(
+            lint_fixture("references.proto", &rules),
+            vec!["9:5: dangling-reference refs.v1.Catalog.first"]
+        );
+    }
+    #[test]
+    fn test_summary() {
+        assert_eq!(summary("First. Second."), "First.");
+        assert_eq!(summary("Version 1.2 is out"), "Version 1.2 is out");
+        assert_eq!(summary("One line\nTwo"), "One line");
+    }
+}
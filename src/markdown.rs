@@ -0,0 +1,219 @@
+//! Minimal Markdown awareness for documentation strings.
+//!
+//! In description options, each line is a paragraph, a list item or a verbatim line
+//! (fenced code, tables and indented code). In comments, paragraphs are separated by
+//! blank lines and prose is wrapped.
+
+/// A block of documentation text
+#[derive(Debug, PartialEq)]
+pub(crate) enum Block {
+    /// Prose, which can be wrapped
+    Paragraph(String),
+    /// A list item: `prefix` is its indentation and marker, e.g. `  - ` or `1. `
+    ListItem { prefix: String, text: String },
+    /// Lines kept as they are: fenced code, tables and indented code
+    Verbatim(Vec<String>),
+}
+
+impl Block {
+    /// The block as a line of a description, or as several for verbatim blocks
+    pub(crate) fn text(&self) -> String {
+        match self {
+            Block::Paragraph(text) => text.clone(),
+            Block::ListItem { prefix, text } => format!("{}{}", prefix, text),
+            Block::Verbatim(lines) => lines.join("\n"),
+        }
+    }
+}
+
+pub(crate) fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+pub(crate) fn is_table_row(line: &str) -> bool {
+    line.trim_start().starts_with('|')
+}
+
+pub(crate) fn is_indented_code(line: &str) -> bool {
+    line.starts_with("    ") || line.starts_with('\t')
+}
+
+/// The length of the indentation and marker of a list item, including the space after the marker
+pub(crate) fn list_prefix_len(line: &str) -> Option<usize> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let marker = if rest.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 || !rest[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let spaces = rest[marker..].len() - rest[marker..].trim_start_matches(' ').len();
+    if spaces == 0 || rest[marker..].trim().is_empty() {
+        return None;
+    }
+    Some(indent + marker + spaces)
+}
+
+/// Split a description into blocks
+/// Each line of a description starts a new block, except inside verbatim blocks
+pub(crate) fn blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        if is_fence(line) {
+            let mut verbatim = vec![line.to_string()];
+            for line in lines.by_ref() {
+                verbatim.push(line.to_string());
+                if is_fence(line) {
+                    break;
+                }
+            }
+            blocks.push(Block::Verbatim(verbatim));
+        } else if line.trim().is_empty() {
+            continue;
+        } else if is_table_row(line) || is_indented_code(line) {
+            let table = is_table_row(line);
+            match blocks.last_mut() {
+                // consecutive rows of a table or lines of code stay together
+                Some(Block::Verbatim(verbatim))
+                    if verbatim.last().is_some_and(|last| is_table_row(last) == table) =>
+                {
+                    verbatim.push(line.to_string())
+                }
+                _ => blocks.push(Block::Verbatim(vec![line.to_string()])),
+            }
+        } else if let Some(len) = list_prefix_len(line) {
+            blocks.push(Block::ListItem {
+                prefix: line[..len].to_string(),
+                text: line[len..].trim().to_string(),
+            });
+        } else {
+            blocks.push(Block::Paragraph(line.trim().to_string()));
+        }
+    }
+    blocks
+}
+
+/// Wrap words into lines of at most `width` bytes, when possible
+pub(crate) fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + word.len() > width {
+            lines.push(line.clone());
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+}
+
+/// Render a description as comment lines (without the comment markers)
+/// Paragraphs are separated by empty lines, and prose is wrapped to `width`
+pub(crate) fn render(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut previous_was_item = false;
+    for block in blocks(text) {
+        let is_item = matches!(block, Block::ListItem { .. });
+        // consecutive list items are not separated
+        let separated = !(is_item && previous_was_item);
+        if separated && !lines.is_empty() {
+            lines.push(String::new());
+        }
+        previous_was_item = is_item;
+        match block {
+            Block::Paragraph(text) => lines.extend(wrap(&text, width)),
+            Block::ListItem { prefix, text } => {
+                let hanging = " ".repeat(prefix.len());
+                for (i, line) in wrap(&text, width.saturating_sub(prefix.len()))
+                    .into_iter()
+                    .enumerate()
+                {
+                    let prefix = if i == 0 { &prefix } else { &hanging };
+                    lines.push(format!("{}{}", prefix, line));
+                }
+            }
+            Block::Verbatim(verbatim) => lines.extend(verbatim),
+        }
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_list_prefix_len() {
+        assert_eq!(list_prefix_len("- item"), Some(2));
+        assert_eq!(list_prefix_len("  * item"), Some(4));
+        assert_eq!(list_prefix_len("12. item"), Some(4));
+        assert_eq!(list_prefix_len("1) item"), Some(3));
+        assert_eq!(list_prefix_len("-item"), None);
+        assert_eq!(list_prefix_len("1.5 percent"), None);
+        assert_eq!(list_prefix_len("- "), None);
+        assert_eq!(list_prefix_len("prose"), None);
+    }
+    #[test]
+    fn test_blocks() {
+        let text = "Intro.\n- a\n- b\n```\nlet x;\n\n  y\n```\n| a | b |\n| 1 | 2 |\n    code\nEnd.";
+        assert_eq!(
+            blocks(text),
+            vec![
+                Block::Paragraph("Intro.".to_string()),
+                Block::ListItem {
+                    prefix: "- ".to_string(),
+                    text: "a".to_string()
+                },
+                Block::ListItem {
+                    prefix: "- ".to_string(),
+                    text: "b".to_string()
+                },
+                Block::Verbatim(vec![
+                    "```".to_string(),
+                    "let x;".to_string(),
+                    "".to_string(),
+                    "  y".to_string(),
+                    "```".to_string()
+                ]),
+                Block::Verbatim(vec!["| a | b |".to_string(), "| 1 | 2 |".to_string()]),
+                Block::Verbatim(vec!["    code".to_string()]),
+                Block::Paragraph("End.".to_string()),
+            ]
+        );
+    }
+    #[test]
+    fn test_render() {
+        let text = "A paragraph that is long enough to be wrapped.\n- an item that is long enough to be wrapped too\n- b\n```\n  code\n```";
+        assert_eq!(
+            render(text, 20),
+            vec![
+                "A paragraph that is",
+                "long enough to be",
+                "wrapped.",
+                "",
+                "- an item that is",
+                "  long enough to be",
+                "  wrapped too",
+                "- b",
+                "",
+                "```",
+                "  code",
+                "```",
+            ]
+        );
+    }
+}
@@ -1,22 +1,27 @@
 
-use std::{io::Write, path::PathBuf};
+use std::path::{Path, PathBuf};
 
+use crate::comments2option::DescriptionIds;
 use crate::editor::Editor;
-use miette::IntoDiagnostic;
-use crate::path_resolver::{tag, prost::PathedChilds, prost::PathedDescriptor};
+use crate::error::{Error, Result};
+use crate::markdown;
+use crate::path_resolver::{tag, prost::elements, prost::PathedChilds, prost::PathedDescriptor};
+use crate::sidecar::{self, Sidecar};
 use prost_reflect::{
-    prost_types::SourceCodeInfo, DynamicMessage, EnumDescriptor, EnumValueDescriptor,
+    prost_types::{source_code_info::Location, SourceCodeInfo},
+    DynamicMessage, EnumDescriptor, EnumValueDescriptor,
     ExtensionDescriptor, FieldDescriptor, FileDescriptor, MessageDescriptor, MethodDescriptor,
-    ServiceDescriptor, Value,
+    OneofDescriptor, ReflectMessage, ServiceDescriptor, Value,
 };
+use protobuf::{well_known_types::empty::Empty, Message, UnknownFields, UnknownValueRef};
 use protox::Compiler;
 use regex::Regex;
 
-#[derive(Debug, clap::Parser)]
+#[derive(Debug, Default, clap::Parser)]
 pub struct Args {
     /// The source file(s) to compile
     #[clap(value_name = "PROTO_FILES", required = true, value_parser)]
-    files: Vec<PathBuf>,
+    pub(crate) files: Vec<PathBuf>,
     /// The directory in which to search for imports.
     #[clap(
         short = 'I',
@@ -26,7 +31,7 @@ pub struct Args {
         default_value = ".",
         value_parser
     )]
-    includes: Vec<PathBuf>,
+    pub(crate) includes: Vec<PathBuf>,
     /// The output path to write the modified files to.
     #[clap(
         short = 'o',
@@ -35,118 +40,588 @@ pub struct Args {
         value_name = "PATH",
         value_parser
     )]
-    output: Option<PathBuf>,
+    pub(crate) output: Option<PathBuf>,
+    /// Do not write anything, fail if some files still carry description options.
+    #[clap(long = "check")]
+    pub(crate) check: bool,
+    /// Do not write anything, print the changes as a unified diff instead.
+    #[clap(long = "diff")]
+    pub(crate) diff: bool,
+    /// Rewrite the source files instead of writing to the output directory.
+    #[clap(long = "in-place", conflicts_with = "output")]
+    pub(crate) in_place: bool,
+    /// Keep a copy of each rewritten file, with this suffix appended to its name.
+    #[clap(long = "backup-suffix", value_name = "SUFFIX", requires = "in_place")]
+    pub(crate) backup_suffix: Option<String>,
+    /// What to do with elements having both a leading comment and a description option.
+    #[clap(long = "merge", value_enum, default_value_t)]
+    pub(crate) merge: MergePolicy,
+    #[clap(flatten)]
+    pub(crate) selector: DescriptionSelector,
+    #[clap(flatten)]
+    pub(crate) format: CommentFormat,
+    /// Insert the descriptions of this JSON or YAML file mapping full names to doc texts,
+    /// e.g. as written by extract, instead of converting description options.
+    #[clap(long = "sidecar", value_name = "PATH", conflicts_with = "check")]
+    pub(crate) sidecar: Option<PathBuf>,
 }
-pub fn entry_point(args: Args) -> miette::Result<()> {
+
+/// How the generated comments are laid out
+#[derive(Debug, Clone, clap::Args)]
+pub struct CommentFormat {
+    /// The column at which generated comments are wrapped.
+    #[clap(long = "wrap-width", value_name = "COLUMNS", default_value_t = 100)]
+    pub(crate) width: usize,
+    /// Do not wrap, keep each string literal of the description on its own comment line.
+    #[clap(long = "no-wrap", conflicts_with = "width")]
+    pub(crate) no_wrap: bool,
+    /// The syntax of the generated comments.
+    #[clap(long = "comment-style", value_enum, default_value_t)]
+    pub(crate) style: CommentStyle,
+}
+impl Default for CommentFormat {
+    fn default() -> Self {
+        Self {
+            width: 100,
+            no_wrap: false,
+            style: CommentStyle::default(),
+        }
+    }
+}
+
+/// The syntax of the generated comments
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum CommentStyle {
+    /// `// ` line comments
+    #[default]
+    Line,
+    /// `/// ` line comments
+    Doc,
+    /// `/** ... */` block comments
+    Block,
+}
+impl CommentStyle {
+    /// The marker starting each line of the comment
+    fn prefix(&self) -> &'static str {
+        match self {
+            CommentStyle::Line => "//",
+            CommentStyle::Doc => "///",
+            CommentStyle::Block => " *",
+        }
+    }
+}
+
+/// What to do when an element already has a leading comment and a description option
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum MergePolicy {
+    /// Replace the existing comment with the description
+    PreferOption,
+    /// Keep the existing comment, and drop the description
+    PreferComment,
+    /// Keep the existing comment, followed by the description as a new paragraph
+    #[default]
+    Concatenate,
+    /// Fail when the comment and the description differ
+    Fail,
+}
+
+/// Selects the extensions holding the descriptions to convert, either by fully-qualified name
+/// or by their number on each options type.
+/// When nothing is selected, any extension whose name ends with "description" is used.
+#[derive(Debug, Default, Clone, clap::Args)]
+pub struct DescriptionSelector {
+    /// The fully-qualified name of a description extension, e.g. `doc.message_description`
+    #[clap(long = "description-extension", value_name = "FULL_NAME")]
+    pub names: Vec<String>,
+    #[clap(flatten)]
+    pub ids: DescriptionIds,
+}
+impl DescriptionSelector {
+    fn matches(&self, ext: &ExtensionDescriptor, id: Option<u32>) -> bool {
+        if self.names.is_empty() && self.ids.is_empty() {
+            return ext.name().ends_with("description");
+        }
+        id == Some(ext.number())
+            || self
+                .names
+                .iter()
+                .any(|name| name.trim_start_matches('.') == ext.full_name())
+    }
+}
+pub fn entry_point(args: Args) -> Result<()> {
     let first_include = args
         .includes
         .first()
         .expect("at least one include dir is expected")
         .clone();
-    let mut compiler = Compiler::new(args.includes)?;
+    let mut compiler = Compiler::new(&args.includes)?;
     compiler.include_imports(false);
     compiler.include_source_info(true);
-    let out_dir = args.output.or_else(|| Some(PathBuf::from("out"))).unwrap();
-    for file_glob in args.files {
+    let out_dir = args.output.clone().unwrap_or_else(|| PathBuf::from("out"));
+    let sidecar = match &args.sidecar {
+        Some(path) => Some(sidecar::load(path)?),
+        None => None,
+    };
+    let mut opened = Vec::new();
+    for file_glob in &args.files {
         let file_glob = file_glob.to_string_lossy();
         let file_glob = shellexpand::tilde(&file_glob);
-        for file in glob::glob(&file_glob).into_diagnostic()? {
-            let file = file.into_diagnostic()?;
+        let files = glob::glob(&file_glob).map_err(|source| Error::Pattern {
+            pattern: file_glob.to_string(),
+            source,
+        })?;
+        for file in files {
+            let file = file.map_err(|err| Error::Io {
+                path: err.path().to_path_buf(),
+                source: err.into_error(),
+            })?;
             let relative = file
                 .strip_prefix(&first_include)
-                .into_diagnostic()?
+                .map_err(|_| Error::NotIncluded {
+                    file: file.clone(),
+                    include: first_include.clone(),
+                })?
                 .to_path_buf();
-            let out_file = out_dir.join(relative.clone());
-            std::fs::create_dir_all(out_file.parent().unwrap()).into_diagnostic()?;
             compiler.open_file(&file)?;
-            let fd = compiler
-                .descriptor_pool()
-                .get_file_by_name(&relative.to_string_lossy())
-                .unwrap();
-            let in_text = std::fs::read_to_string(&file).into_diagnostic()?;
-            let mut out = std::fs::File::create(&out_file).into_diagnostic()?;
-            insert_comments(&fd, &in_text, &mut out)?;
-            println!("wrote file: {}", out_file.to_string_lossy());
-
+            opened.push((file, relative));
+        }
+    }
+    let pool = compiler.descriptor_pool();
+    if let (Some(path), Some(sidecar)) = (&args.sidecar, &sidecar) {
+        let known: Vec<String> = opened
+            .iter()
+            .filter_map(|(_, relative)| pool.get_file_by_name(&relative.to_string_lossy()))
+            .flat_map(|fd| {
+                let names: Vec<String> = elements(&fd)
+                    .iter()
+                    .map(|(_, pathed)| describe(pathed).1)
+                    .collect();
+                std::iter::once(fd.name().to_string()).chain(names)
+            })
+            .collect();
+        sidecar::check_names(sidecar, &known, path)?;
+    }
+    let mut pending_files = 0;
+    for (file, relative) in opened {
+        let name = relative.to_string_lossy();
+        let fd = pool
+            .get_file_by_name(&name)
+            .ok_or_else(|| Error::MissingFile {
+                name: name.to_string(),
+            })?;
+        let in_text = std::fs::read_to_string(&file).map_err(Error::io(&file))?;
+        let (out_text, converted) = match &sidecar {
+            Some(sidecar) => insert_sidecar_comments(&fd, &in_text, sidecar, &args)?,
+            None => insert_comments(&fd, &in_text, &args)?,
+        };
+        if args.diff {
+            print!("{}", unified_diff(&relative, &in_text, &out_text));
+        }
+        if args.check {
+            for element in &converted {
+                println!(
+                    "{}:{}: {} {} has a description option",
+                    relative.to_string_lossy(),
+                    element.line,
+                    element.kind,
+                    element.name
+                );
+            }
+            if !converted.is_empty() {
+                pending_files += 1;
+            }
         }
+        if args.check || args.diff {
+            continue;
+        }
+        println!("inserted {} comments", converted.len());
+        if args.in_place {
+            if converted.is_empty() {
+                continue;
+            }
+            if let Some(suffix) = &args.backup_suffix {
+                let mut backup = file.clone().into_os_string();
+                backup.push(suffix);
+                std::fs::copy(&file, &backup).map_err(Error::io(backup))?;
+            }
+            write_atomic(&file, &out_text).map_err(Error::io(&file))?;
+            println!("rewrote file: {}", file.to_string_lossy());
+            continue;
+        }
+        let out_file = out_dir.join(relative.clone());
+        let out_parent = out_file.parent().unwrap();
+        std::fs::create_dir_all(out_parent).map_err(Error::io(out_parent))?;
+        std::fs::write(&out_file, out_text).map_err(Error::io(&out_file))?;
+        println!("wrote file: {}", out_file.to_string_lossy());
+    }
+    if pending_files > 0 {
+        return Err(Error::Pending {
+            count: pending_files,
+        });
     }
     Ok(())
 }
+
+/// Write to a temporary file next to `path`, then rename it over `path`,
+/// so that an interrupted run never leaves a half-written file
+fn write_atomic(path: &Path, text: &str) -> std::io::Result<()> {
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    let res = std::fs::write(&tmp, text)
+        .and_then(|_| std::fs::set_permissions(&tmp, std::fs::metadata(path)?.permissions()))
+        .and_then(|_| std::fs::File::open(&tmp)?.sync_all())
+        .and_then(|_| std::fs::rename(&tmp, path));
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    res
+}
+
+/// A unified diff between the original and the converted text,
+/// with file headers relative to the include root
+fn unified_diff(relative: &Path, old: &str, new: &str) -> String {
+    let name = relative.to_string_lossy();
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(&format!("a/{}", name), &format!("b/{}", name))
+        .to_string()
+}
+
+/// An element whose description option is turned into a comment
+#[derive(Debug)]
+struct Converted {
+    kind: &'static str,
+    name: String,
+    line: usize,
+}
+
+/// Turn the description options of a file into comments
+/// Returns the new text of the file, and the converted elements
 fn insert_comments(
     fd: &FileDescriptor,
     in_text: &str,
-    out: &mut std::fs::File,
-) -> miette::Result<()> {
+    args: &Args,
+) -> Result<(String, Vec<Converted>)> {
+    let selector = &args.selector;
     let source_info = fd
         .file_descriptor_proto()
         .source_code_info
         .as_ref()
         .unwrap();
     let mut editor = Editor::new(in_text.to_string());
-    let mut count = 0;
+    let mut converted = Vec::new();
+    let whitespace = Regex::new(r"[\s]+").unwrap();
+    // errors coming from the source point at it
+    let in_source = |at: (usize, usize)| move |err: Error| err.in_source(fd.name(), in_text, at.into());
     for loc in source_info.location.iter() {
         if let Some(pathed) = fd.get_child_from_loc(loc) {
-            if let Some(ext) = get_description(&pathed) {
-                count += 1;
+            let element = location_span(&editor, &loc.span).unwrap_or_default();
+            if let Some(ext) = get_description(&pathed, selector).map_err(in_source(element))? {
+                let (kind, name) = describe(&pathed);
                 let start_line = loc.span[0] as usize;
                 let start_col = loc.span[1] as usize;
                 let start = editor.get_position(start_line, start_col);
-                        let spaces = &in_text[start - start_col..start];
-                let comment = ext.value.as_str().unwrap().to_string();
+                let spaces = &in_text[start - start_col..start];
                 let mut to_remove_path = loc.path.clone();
                 to_remove_path.push(get_option(&pathed));
-                to_remove_path.push(ext.desc.number() as i32);
-                let (position, length) =
-                    find_to_delete_span(&editor, &source_info, &to_remove_path);
-                let (position, length) = match pathed {
-                    PathedDescriptor::Field(_) | PathedDescriptor::EnumValue(_) => {
-                        let (start, len) = eat_syntax_around(&editor, position, length);
-                        (start, len)
-                    }
-                    _ => {
-                        // skip white space after
-                        (
-                            position,
-                            length
-                                + skip_regex(
-                                    &Regex::new(r"[\s]+").unwrap(),
-                                    &editor.text()[position + length..],
-                                ),
-                        )
-                    }
-                };
-                editor.delete(position, length);
-                editor.insert(start, format_comment(comment, spaces));
+                to_remove_path.push(ext.number as i32);
+                let spans = find_to_delete_spans(&editor, &source_info, &to_remove_path);
+                let (position, length) = covering_span(&spans);
+                let comment = description_text(&ext, &name).map_err(in_source((position, length)))?;
+                let option_text = &in_text[position..position + length];
+                let lines = comment_lines(&comment, option_text, spaces, &args.format);
+                for (position, length) in spans {
+                    let (position, length) = match pathed {
+                        PathedDescriptor::Field(_)
+                        | PathedDescriptor::EnumValue(_)
+                        | PathedDescriptor::Extension(_) => {
+                            let (start, len) = eat_syntax_around(&editor, position, length);
+                            (start, len)
+                        }
+                        _ => {
+                            // skip white space after
+                            (
+                                position,
+                                length
+                                    + skip_regex(
+                                        &whitespace,
+                                        &editor.text()[position + length..],
+                                    ),
+                            )
+                        }
+                    };
+                    editor.delete(position, length);
+                }
+                place_comment(&mut editor, loc, start, spaces, lines, &name, args)
+                    .map_err(in_source(element))?;
+                converted.push(Converted {
+                    kind,
+                    name,
+                    line: loc.span[0] as usize + 1,
+                });
             }
         }
     }
+    if let Some(ext) = select_description(fd.name(), &fd.options(), selector, selector.ids.file)? {
+        let to_remove_path = [tag::file::OPTIONS, ext.number as i32];
+        let spans = find_to_delete_spans(&editor, &source_info, &to_remove_path);
+        let (position, length) = covering_span(&spans);
+        let comment = description_text(&ext, fd.name()).map_err(in_source((position, length)))?;
+        let option_text = &in_text[position..position + length];
+        let lines = comment_lines(&comment, option_text, "", &args.format);
+        converted.push(Converted {
+            kind: "file",
+            name: fd.name().to_string(),
+            line: editor.text()[..position].lines().count() + 1,
+        });
+        let end_of_line = Regex::new(r"^[ \t]*\n?").unwrap();
+        for (position, length) in spans {
+            // top level statements: only remove the rest of the line
+            let length = length + skip_regex(&end_of_line, &editor.text()[position + length..]);
+            editor.delete(position, length);
+        }
+        place_file_comment(&mut editor, fd, in_text, lines, args)?;
+    }
     editor.apply();
-    println!("inserted {} comments", count);
-    out.write_all(editor.text().as_bytes()).into_diagnostic()?;
+    converted.sort_by_key(|element| element.line);
+    Ok((editor.text().to_string(), converted))
+}
+
+/// Insert the descriptions of `sidecar` as comments of the elements of a file
+/// Returns the new text of the file, and the documented elements
+fn insert_sidecar_comments(
+    fd: &FileDescriptor,
+    in_text: &str,
+    sidecar: &Sidecar,
+    args: &Args,
+) -> Result<(String, Vec<Converted>)> {
+    let source_info = fd
+        .file_descriptor_proto()
+        .source_code_info
+        .as_ref()
+        .unwrap();
+    let mut editor = Editor::new(in_text.to_string());
+    let mut converted = Vec::new();
+    for loc in source_info.location.iter() {
+        let Some(pathed) = fd.get_child_from_loc(loc) else {
+            continue;
+        };
+        let (kind, name) = describe(&pathed);
+        let Some(description) = sidecar.get(&name) else {
+            continue;
+        };
+        let start_col = loc.span[1] as usize;
+        let start = editor.get_position(loc.span[0] as usize, start_col);
+        let spaces = &in_text[start - start_col..start];
+        let lines = comment_lines(description, "", spaces, &args.format);
+        let element = location_span(&editor, &loc.span).unwrap_or_default();
+        place_comment(&mut editor, loc, start, spaces, lines, &name, args)
+            .map_err(|err| err.in_source(fd.name(), in_text, element.into()))?;
+        converted.push(Converted {
+            kind,
+            name,
+            line: loc.span[0] as usize + 1,
+        });
+    }
+    if let Some(description) = sidecar.get(fd.name()) {
+        let lines = comment_lines(description, "", "", &args.format);
+        place_file_comment(&mut editor, fd, in_text, lines, args)?;
+        converted.push(Converted {
+            kind: "file",
+            name: fd.name().to_string(),
+            line: 1,
+        });
+    }
+    editor.apply();
+    converted.sort_by_key(|element| element.line);
+    Ok((editor.text().to_string(), converted))
+}
+
+/// Insert the file description `lines` above the syntax statement, or at the top of the file
+fn place_file_comment(
+    editor: &mut Editor,
+    fd: &FileDescriptor,
+    in_text: &str,
+    lines: Vec<String>,
+    args: &Args,
+) -> Result<()> {
+    let syntax = fd
+        .file_descriptor_proto()
+        .source_code_info
+        .as_ref()
+        .and_then(|info| info.location.iter().find(|loc| loc.path == [tag::file::SYNTAX]));
+    match syntax {
+        Some(loc) => {
+            let start = editor.get_position(loc.span[0] as usize, loc.span[1] as usize);
+            let element = location_span(editor, &loc.span).unwrap_or_default();
+            place_comment(editor, loc, start, "", lines, fd.name(), args)
+                .map_err(|err| err.in_source(fd.name(), in_text, element.into()))?;
+        }
+        None => editor.insert(0, format_comment(&lines, "", args.format.style)),
+    }
     Ok(())
 }
 
-fn find_to_delete_span(
+/// Insert the description `lines` as a comment above the element starting at `start`,
+/// merging them with the existing leading comment according to `args.merge`
+fn place_comment(
+    editor: &mut Editor,
+    loc: &Location,
+    start: usize,
+    spaces: &str,
+    lines: Vec<String>,
+    name: &str,
+    args: &Args,
+) -> Result<()> {
+    let style = args.format.style;
+    let existing = loc
+        .leading_comments
+        .as_deref()
+        .zip(leading_comment_span(editor.text(), start));
+    let Some((existing, (position, length))) = existing else {
+        editor.insert(start, format_comment(&lines, spaces, style));
+        return Ok(());
+    };
+    let words = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+    if words(existing) == words(&lines.join(" ")) {
+        // the comment already says it all
+        return Ok(());
+    }
+    match args.merge {
+        MergePolicy::PreferComment => {}
+        MergePolicy::PreferOption => {
+            editor.delete(position, length);
+            editor.insert(start, format_comment(&lines, spaces, style));
+        }
+        MergePolicy::Concatenate => {
+            let block = editor.text()[position..].trim_start().starts_with("/*");
+            let mut merged = existing_lines(existing, block);
+            merged.push(String::new());
+            merged.extend(lines);
+            editor.delete(position, length);
+            editor.insert(start, format_comment(&merged, spaces, style));
+        }
+        MergePolicy::Fail => {
+            return Err(Error::MergeConflict {
+                name: name.to_string(),
+                src: None,
+                span: None,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The lines of an existing comment, as reported by protox, without their comment markers
+/// Block comments lose their inner indentation
+fn existing_lines(existing: &str, block: bool) -> Vec<String> {
+    let doc = existing
+        .lines()
+        .all(|line| line.is_empty() || line.starts_with('/'));
+    let mut lines: Vec<String> = existing
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if block {
+                let line = line.trim();
+                // the `*` of `/**`
+                let line = if i == 0 { line.trim_start_matches('*') } else { line };
+                line.trim().to_string()
+            } else {
+                let line = if doc { &line[line.len().min(1)..] } else { line };
+                line.strip_prefix(' ').unwrap_or(line).trim_end().to_string()
+            }
+        })
+        .collect();
+    while lines.first().is_some_and(|line| line.is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Find the comment block right above the line of `position`
+/// Returns the position and length of the lines holding it
+fn leading_comment_span(text: &str, position: usize) -> Option<(usize, usize)> {
+    let line_start = text[..position].rfind('\n').map_or(0, |i| i + 1);
+    let mut start = line_start;
+    while start > 0 {
+        let before = &text[..start];
+        let previous = before[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        let line = before[previous..].trim();
+        if line.starts_with("//") {
+            start = previous;
+        } else if line.ends_with("*/") {
+            // the block must start its own line, unlike a trailing comment after code
+            let Some(open) = before.rfind("/*") else {
+                break;
+            };
+            let open_line = before[..open].rfind('\n').map_or(0, |i| i + 1);
+            if !before[open_line..open].trim().is_empty() {
+                break;
+            }
+            start = open_line;
+        } else {
+            break;
+        }
+    }
+    (start < line_start).then(|| (start, line_start - start))
+}
+
+/// The spans of the option at `to_remove_path`, one per value for repeated options
+/// Values only separated by commas and white space are merged into a single span
+fn find_to_delete_spans(
     editor: &Editor,
     source_info: &&SourceCodeInfo,
     to_remove_path: &[i32],
-) -> (usize, usize) {
+) -> Vec<(usize, usize)> {
+    let separator = Regex::new(r"^[\s,]*$").unwrap();
+    let mut spans: Vec<(usize, usize)> = Vec::new();
     for loc in source_info.location.iter() {
-        if loc.path == *to_remove_path {
-            let span = &loc.span;
-            let start_line = span[0] as usize;
-            let start_col = span[1] as usize;
-            let (end_line, end_col) = match span.len() {
-                3 => (span[0] as usize, span[2] as usize),
-                4 => (span[2] as usize, span[3] as usize),
-                _ => return (0, 0),
-            };
-            let start = editor.get_position(start_line, start_col);
-            let end = editor.get_position(end_line, end_col);
-            return (start, end - start);
+        // repeated values have their index appended to the path
+        let is_value = loc.path.starts_with(to_remove_path)
+            && loc.path.len() <= to_remove_path.len() + 1;
+        if !is_value {
+            continue;
+        }
+        let Some((start, length)) = location_span(editor, &loc.span) else {
+            continue;
+        };
+        match spans.last_mut() {
+            Some((position, len))
+                if *position + *len <= start
+                    && separator.is_match(&editor.text()[*position + *len..start]) =>
+            {
+                *len = start + length - *position;
+            }
+            _ => spans.push((start, length)),
         }
     }
-    (0, 0)
+    spans
+}
+/// The span going from the first to the last of `spans`
+fn covering_span(spans: &[(usize, usize)]) -> (usize, usize) {
+    match (spans.first(), spans.last()) {
+        (Some(first), Some(last)) => (first.0, last.0 + last.1 - first.0),
+        _ => (0, 0),
+    }
+}
+/// The position and length of a source info span, in the original text
+fn location_span(editor: &Editor, span: &[i32]) -> Option<(usize, usize)> {
+    let start_line = span[0] as usize;
+    let start_col = span[1] as usize;
+    let (end_line, end_col) = match span.len() {
+        3 => (span[0] as usize, span[2] as usize),
+        4 => (span[2] as usize, span[3] as usize),
+        _ => return None,
+    };
+    let start = editor.get_position(start_line, start_col);
+    let end = editor.get_position(end_line, end_col);
+    Some((start, end - start))
 }
 fn skip_regex(regex: &Regex, text: &str) -> usize {
     if let Some(match_) = regex.find(text) {
@@ -181,81 +656,255 @@ fn eat_syntax_around(editor: &Editor, start: usize, len: usize) -> (usize, usize
     (start, end - start)
 }
 
-/// Format a comment to fit within 100 characters
-/// and add the correct padding
-fn format_comment(comment: String, spaces: &str) -> String {
-    let mut lines = Vec::new();
-    let mut line = String::new();
-    let padding_size = spaces.len() + 4;
-    for word in comment.split_whitespace() {
-        if line.len() + word.len() + padding_size > 100 {
-            lines.push(line.clone());
-            line.clear();
+/// The lines of the comment for `description`, without comment markers
+/// They are wrapped to fit within `format.width` characters, given the indentation and the comment
+/// markers, and keep the paragraphs, lists and verbatim blocks of the description, see [`markdown`]
+/// In no-wrap mode, each string literal found in `option_text` is a line of its own.
+fn comment_lines(
+    description: &str,
+    option_text: &str,
+    spaces: &str,
+    format: &CommentFormat,
+) -> Vec<String> {
+    if format.no_wrap {
+        let literals = string_literals(option_text);
+        if !literals.is_empty() {
+            let mut lines: Vec<String> = literals
+                .iter()
+                .flat_map(|literal| literal.split('\n'))
+                .map(|line| line.trim().to_string())
+                .collect();
+            // a final "\n" does not start a new line
+            while lines.len() > 1 && lines.last().is_some_and(|line| line.is_empty()) {
+                lines.pop();
+            }
+            return lines;
+        }
+    }
+    let width = format
+        .width
+        .saturating_sub(spaces.len() + format.style.prefix().len() + 2);
+    markdown::render(description, width)
+}
+
+/// The unescaped values of the string literals in `text`
+fn string_literals(text: &str) -> Vec<String> {
+    let literal = Regex::new(r#""((?:[^"\\]|\\.)*)"|'((?:[^'\\]|\\.)*)'"#).unwrap();
+    literal
+        .captures_iter(text)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .map(|value| unescape(value.as_str()))
+        .collect()
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
         }
-        if !line.is_empty() {
-            line.push(' ');
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
         }
-        line.push_str(word);
     }
-    lines.push(line);
+    unescaped
+}
+
+/// Render comment `lines` with the comment markers of `style`, and add the correct padding
+fn format_comment(lines: &[String], spaces: &str, style: CommentStyle) -> String {
     let mut formatted = String::new();
-    for (i, line) in lines.iter().enumerate() {
-        if i > 0 {
-            formatted.push_str(spaces);
-        }
-        formatted.push_str("// ");
+    let mut push = |line: &str| {
         formatted.push_str(line);
         formatted.push('\n');
+        formatted.push_str(spaces);
+    };
+    if style == CommentStyle::Block {
+        push("/**");
+    }
+    for line in lines {
+        if line.is_empty() {
+            push(style.prefix());
+        } else {
+            push(&format!("{} {}", style.prefix(), line));
+        }
+    }
+    if style == CommentStyle::Block {
+        push(" */");
     }
-    formatted.push_str(spaces);
     formatted
 }
 
+/// A description option: a declared extension, or an unknown field of a configured id
 #[derive(Debug)]
-struct Ext {
-    desc: ExtensionDescriptor,
+pub(crate) struct Ext {
+    name: String,
+    number: u32,
     value: Value,
 }
 
+/// The text of a description
+/// Strings and UTF-8 bytes are used as is, each value of a repeated description is a paragraph,
+/// and so is each text field of a message, its `summary` first.
+pub(crate) fn description_text(ext: &Ext, name: &str) -> Result<String> {
+    value_text(&ext.value).ok_or_else(|| Error::NotText {
+        name: name.to_string(),
+        src: None,
+        span: None,
+    })
+}
+fn value_text(value: &Value) -> Option<String> {
+    let paragraphs = |values: Vec<Value>| {
+        let texts = values.iter().map(value_text).collect::<Option<Vec<_>>>()?;
+        let texts: Vec<String> = texts.into_iter().filter(|text| !text.is_empty()).collect();
+        Some(texts.join("\n"))
+    };
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Bytes(bytes) => String::from_utf8(bytes.to_vec()).ok(),
+        Value::List(values) => paragraphs(values.clone()),
+        Value::Message(message) => {
+            let mut fields: Vec<FieldDescriptor> = message
+                .descriptor()
+                .fields()
+                .filter(|field| message.has_field(field))
+                .collect();
+            fields.sort_by_key(|field| field.name() != "summary");
+            paragraphs(
+                fields
+                    .iter()
+                    .map(|field| message.get_field(field).into_owned())
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
 trait Described {
-    fn get_description(&self) -> Option<Ext>;
-}
-impl Described for DynamicMessage {
-    fn get_description(&self) -> Option<Ext> {
-        self.extensions()
-            .find(|ext| ext.0.name().ends_with("description"))
-            .map(|(ed, v)| Ext {
-                desc: ed,
-                value: v.clone(),
-            })
+    fn get_description(&self, selector: &DescriptionSelector) -> Result<Option<Ext>>;
+}
+/// Look for the description among the extensions set on `options`
+/// `id` is the number configured for the options type of the element
+pub(crate) fn select_description(
+    name: &str,
+    options: &DynamicMessage,
+    selector: &DescriptionSelector,
+    id: Option<u32>,
+) -> Result<Option<Ext>> {
+    let mut candidates: Vec<Ext> = options
+        .extensions()
+        .filter(|(ed, _)| selector.matches(ed, id))
+        .map(|(ed, v)| Ext {
+            name: ed.full_name().to_string(),
+            number: ed.number(),
+            value: v.clone(),
+        })
+        .collect();
+    // the extension of the id may not be declared, e.g. in a descriptor set from comments2option
+    if let Some(id) = id {
+        let mut values: Vec<Value> = unknown_values(&unknown_fields(options), id)
+            .into_iter()
+            .map(|bytes| Value::Bytes(bytes.into()))
+            .collect();
+        let value = match values.len() {
+            0 => None,
+            1 => values.pop(),
+            _ => Some(Value::List(values)),
+        };
+        if let Some(value) = value {
+            candidates.push(Ext {
+                name: format!("field {}", id),
+                number: id,
+                value,
+            });
+        }
+    }
+    if candidates.len() > 1 {
+        return Err(Error::Ambiguous {
+            name: name.to_string(),
+            candidates: candidates
+                .iter()
+                .map(|ext| ext.name.clone())
+                .collect(),
+            src: None,
+            span: None,
+        });
     }
+    Ok(candidates.pop())
+}
+/// The unknown fields of `options`, i.e. the options whose extension is not in the pool
+fn unknown_fields(options: &DynamicMessage) -> UnknownFields {
+    let mut bytes = Vec::new();
+    for field in options.unknown_fields() {
+        field.encode(&mut bytes);
+    }
+    Empty::parse_from_bytes(&bytes)
+        .map(|empty| empty.special_fields.unknown_fields().clone())
+        .unwrap_or_default()
+}
+/// The length-delimited values of the field `id` among `unknown`, in order
+/// A repeated description has a value per paragraph.
+pub(crate) fn unknown_values(unknown: &UnknownFields, id: u32) -> Vec<Vec<u8>> {
+    unknown
+        .iter()
+        .filter(|(number, _)| *number == id)
+        .filter_map(|(_, value)| match value {
+            UnknownValueRef::LengthDelimited(bytes) => Some(bytes.to_vec()),
+            _ => None,
+        })
+        .collect()
 }
 macro_rules! impl_commented {
-    ($($t:ty),*) => {
+    ($($t:ty => $id:ident),*) => {
         $(impl Described for $t {
-            fn get_description(&self) -> Option<Ext> {
-                self.options().get_description()
+            fn get_description(&self, selector: &DescriptionSelector) -> Result<Option<Ext>> {
+                select_description(self.full_name(), &self.options(), selector, selector.ids.$id)
             }
         })*
     };
 }
 impl_commented!(
-    MessageDescriptor,
-    EnumDescriptor,
-    EnumValueDescriptor,
-    FieldDescriptor,
-    ServiceDescriptor,
-    MethodDescriptor
+    MessageDescriptor => message,
+    EnumDescriptor => enum_,
+    EnumValueDescriptor => enum_value,
+    FieldDescriptor => field,
+    ServiceDescriptor => service,
+    MethodDescriptor => method,
+    OneofDescriptor => oneof,
+    ExtensionDescriptor => extension
 );
-fn get_description(pathed: &PathedDescriptor) -> Option<Ext> {
+pub(crate) fn get_description(
+    pathed: &PathedDescriptor,
+    selector: &DescriptionSelector,
+) -> Result<Option<Ext>> {
     match pathed {
-        PathedDescriptor::Message(m) => m.get_description(),
-        PathedDescriptor::Enum(e) => e.get_description(),
-        PathedDescriptor::Service(s) => s.get_description(),
-        PathedDescriptor::Method(m) => m.get_description(),
-        PathedDescriptor::Field(f) => f.get_description(),
-        PathedDescriptor::EnumValue(e) => e.get_description(),
-        _ => None,
+        PathedDescriptor::Message(m) => m.get_description(selector),
+        PathedDescriptor::Enum(e) => e.get_description(selector),
+        PathedDescriptor::Service(s) => s.get_description(selector),
+        PathedDescriptor::Method(m) => m.get_description(selector),
+        PathedDescriptor::Field(f) => f.get_description(selector),
+        PathedDescriptor::EnumValue(e) => e.get_description(selector),
+        PathedDescriptor::Oneof(o) => o.get_description(selector),
+        PathedDescriptor::Extension(e) => e.get_description(selector),
+        _ => Ok(None),
+    }
+}
+pub(crate) fn describe(pathed: &PathedDescriptor) -> (&'static str, String) {
+    match pathed {
+        PathedDescriptor::Message(m) => ("message", m.full_name().to_string()),
+        PathedDescriptor::Enum(e) => ("enum", e.full_name().to_string()),
+        PathedDescriptor::Service(s) => ("service", s.full_name().to_string()),
+        PathedDescriptor::Method(m) => ("method", m.full_name().to_string()),
+        PathedDescriptor::Field(f) => ("field", f.full_name().to_string()),
+        PathedDescriptor::EnumValue(e) => ("enum value", e.full_name().to_string()),
+        PathedDescriptor::Oneof(o) => ("oneof", o.full_name().to_string()),
+        PathedDescriptor::Extension(e) => ("extension", e.full_name().to_string()),
+        _ => ("option", String::new()),
     }
 }
 fn get_option(pathed: &PathedDescriptor) -> i32 {
@@ -266,6 +915,8 @@ fn get_option(pathed: &PathedDescriptor) -> i32 {
         PathedDescriptor::Method(_) => tag::method::OPTIONS,
         PathedDescriptor::Field(_) => tag::field::OPTIONS,
         PathedDescriptor::EnumValue(_) => tag::enum_value::OPTIONS,
+        PathedDescriptor::Oneof(_) => tag::oneof::OPTIONS,
+        PathedDescriptor::Extension(_) => tag::field::OPTIONS,
         _ => 0,
     }
 }
@@ -275,11 +926,13 @@ mod test {
     use super::*;
     use rand;
     use pretty_assertions::assert_eq;
+    use crate::tests::fixtures::fixtures;
     #[test]
     fn test_format_comment() {
-        let comment = "This is a long comment that should be split into multiple lines to fit within 100 characters".to_string();
+        let comment = "This is a long comment that should be split into multiple lines to fit within 100 characters";
         let spaces = "    ";
-        let formatted = format_comment(comment, spaces);
+        let lines = comment_lines(comment, "", spaces, &CommentFormat::default());
+        let formatted = format_comment(&lines, spaces, CommentStyle::Line);
         assert_eq!(formatted.lines().count(), 2);
     }
     fn eat_around_test(text: &str, expected: &str) {
@@ -302,17 +955,22 @@ mod test {
     }
 
     fn run_fixture_test(fixture: &str) {
-        let mut fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        fixtures.push("src/fixtures");
+        run_fixture_test_with(fixture, "expected.proto", |_| {});
+    }
+    /// `customize` can change the default arguments, `expected` is the extension of the expected file
+    fn run_fixture_test_with(fixture: &str, expected: &str, customize: impl FnOnce(&mut Args)) {
+        let fixtures = fixtures();
         let path = fixtures.join(fixture);
         let temp_output_dir = std::env::temp_dir().join(rand::random::<u64>().to_string());
-        let args = Args {
+        let mut args = Args {
             files: vec![path],
             includes: vec![fixtures.clone()],
             output: Some(temp_output_dir.clone()),
+            ..Default::default()
         };
+        customize(&mut args);
         entry_point(args).unwrap();
-        let expected_path = fixtures.join(fixture).with_extension("expected.proto");
+        let expected_path = fixtures.join(fixture).with_extension(expected);
         let actual = std::fs::read_to_string(temp_output_dir.join(fixture)).unwrap();
         // std::fs::remove_file(expected_path.clone()).unwrap();
         if expected_path.exists() {
@@ -332,6 +990,40 @@ mod test {
         run_fixture_test("basic.proto");
     }
     #[test]
+    fn test_doc_style() {
+        run_fixture_test_with("basic.proto", "doc.expected.proto", |args| {
+            args.format.style = CommentStyle::Doc;
+        });
+    }
+    #[test]
+    fn test_block_style() {
+        run_fixture_test_with("markdown.proto", "block.expected.proto", |args| {
+            args.format.style = CommentStyle::Block;
+        });
+    }
+    #[test]
+    fn test_no_wrap() {
+        run_fixture_test_with("multiline.proto", "no_wrap.expected.proto", |args| {
+            args.format.no_wrap = true;
+        });
+    }
+    #[test]
+    fn test_wrap_width() {
+        let format = CommentFormat {
+            width: 30,
+            ..Default::default()
+        };
+        let lines = comment_lines("This is a comment wrapped to a narrow width", "", "  ", &format);
+        assert_eq!(lines, vec!["This is a comment wrapped", "to a narrow width"]);
+    }
+    #[test]
+    fn test_string_literals() {
+        assert_eq!(
+            string_literals(r#"(doc.d) = "a \"b\"\n" 'c'"#),
+            vec!["a \"b\"\n".to_string(), "c".to_string()]
+        );
+    }
+    #[test]
     fn test_nested() {
         run_fixture_test("nested.proto");
     }
@@ -343,4 +1035,237 @@ mod test {
     fn test_multiline() {
         run_fixture_test("multiline.proto");
     }
+    #[test]
+    fn test_markdown() {
+        run_fixture_test("markdown.proto");
+    }
+    #[test]
+    fn test_oneof_extension() {
+        run_fixture_test("oneof_extension.proto");
+    }
+    #[test]
+    fn test_file() {
+        run_fixture_test("file.proto");
+    }
+    #[test]
+    fn test_syntax_comment() {
+        run_fixture_test("syntax_comment.proto");
+    }
+    #[test]
+    fn test_merge_concatenate() {
+        run_fixture_test("merge.proto");
+    }
+    #[test]
+    fn test_merge_trailing_block() {
+        run_fixture_test("merge_trailing.proto");
+        run_fixture_test_with("merge_trailing.proto", "prefer_option.expected.proto", |args| {
+            args.merge = MergePolicy::PreferOption;
+        });
+    }
+    #[test]
+    fn test_merge_prefer_option() {
+        run_fixture_test_with("merge.proto", "prefer_option.expected.proto", |args| {
+            args.merge = MergePolicy::PreferOption;
+        });
+    }
+    #[test]
+    fn test_merge_prefer_comment() {
+        run_fixture_test_with("merge.proto", "prefer_comment.expected.proto", |args| {
+            args.merge = MergePolicy::PreferComment;
+        });
+    }
+    #[test]
+    fn test_merge_fail() {
+        let fixtures = fixtures();
+        let err = entry_point(Args {
+            files: vec![fixtures.join("merge.proto")],
+            includes: vec![fixtures.clone()],
+            check: true,
+            merge: MergePolicy::Fail,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.MyMessage has both a comment and a different description option"
+        );
+    }
+    #[test]
+    fn test_sidecar() {
+        run_fixture_test_with("sidecar.proto", "expected.proto", |args| {
+            args.sidecar = Some(args.includes[0].join("sidecar.yaml"));
+        });
+    }
+    #[test]
+    fn test_typed_descriptions() {
+        run_fixture_test_with("typed.proto", "expected.proto", |args| {
+            args.selector.names = vec![
+                "test.info".to_string(),
+                "test.paragraphs".to_string(),
+                "test.raw_description".to_string(),
+            ];
+        });
+    }
+    #[test]
+    fn test_not_a_string() {
+        let fixtures = fixtures();
+        let err = entry_point(Args {
+            files: vec![fixtures.join("not_a_string.proto")],
+            includes: vec![fixtures.clone()],
+            check: true,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "the description of test.MyMessage is not text");
+        let Error::NotText {
+            src: Some(src),
+            span: Some(span),
+            ..
+        } = err
+        else {
+            panic!("no source span in {:?}", err);
+        };
+        assert_eq!(src.name(), "not_a_string.proto");
+        let text = std::fs::read_to_string(fixtures.join("not_a_string.proto")).unwrap();
+        assert_eq!(
+            &text[span.offset()..span.offset() + span.len()],
+            "option (test.priority_description) = 3;"
+        );
+    }
+    #[test]
+    fn test_leading_comment_span() {
+        let text = "a;\n// one\n  // two\nmessage A {}";
+        let (position, length) = leading_comment_span(text, text.find("message").unwrap()).unwrap();
+        assert_eq!(&text[position..position + length], "// one\n  // two\n");
+        let text = "a;\n/* one\n two */\n  message A {}";
+        let (position, length) = leading_comment_span(text, text.find("message").unwrap()).unwrap();
+        assert_eq!(&text[position..position + length], "/* one\n two */\n");
+        assert_eq!(leading_comment_span("a;\n\nmessage A {}", 4), None);
+        // a block comment trailing code is not part of the comment
+        let text = "a; /* one */\n// two\nmessage A {}";
+        let (position, length) = leading_comment_span(text, text.find("message").unwrap()).unwrap();
+        assert_eq!(&text[position..position + length], "// two\n");
+        let text = "/* a */\nb; /* one\n two */\nmessage A {}";
+        assert_eq!(leading_comment_span(text, text.find("message").unwrap()), None);
+    }
+    #[test]
+    fn test_ambiguous() {
+        let fixtures = fixtures();
+        let temp_output_dir = std::env::temp_dir().join(rand::random::<u64>().to_string());
+        let args = Args {
+            files: vec![fixtures.join("ambiguous.proto")],
+            includes: vec![fixtures.clone()],
+            output: Some(temp_output_dir.clone()),
+            ..Default::default()
+        };
+        let err = entry_point(args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.MyMessage has several description options: doc.message_description, test.short_description"
+        );
+        let _ = std::fs::remove_dir_all(temp_output_dir);
+    }
+    #[test]
+    fn test_select_by_name() {
+        run_fixture_test_with("ambiguous.proto", "expected.proto", |args| {
+            args.selector.names = vec!["doc.message_description".to_string()];
+        });
+    }
+    #[test]
+    fn test_select_by_id() {
+        run_fixture_test_with("ambiguous.proto", "expected.proto", |args| {
+            args.selector.ids.message = Some(1000);
+        });
+    }
+    #[test]
+    fn test_select_undeclared_id() {
+        // options from a descriptor set whose description extensions are not declared
+        let desc = prost_reflect::DescriptorPool::global()
+            .get_message_by_name("google.protobuf.MessageOptions")
+            .unwrap();
+        let mut bytes = Vec::new();
+        for paragraph in ["A message.", "Its second paragraph."] {
+            bytes.extend([0xc2, 0x3e, paragraph.len() as u8]);
+            bytes.extend(paragraph.as_bytes());
+        }
+        let options = DynamicMessage::decode(desc, bytes.as_slice()).unwrap();
+        let mut selector = DescriptionSelector::default();
+        assert!(select_description("test.MyMessage", &options, &selector, None)
+            .unwrap()
+            .is_none());
+        selector.ids.message = Some(1000);
+        let ext = select_description("test.MyMessage", &options, &selector, Some(1000))
+            .unwrap()
+            .unwrap();
+        assert_eq!(ext.number, 1000);
+        assert_eq!(
+            description_text(&ext, "test.MyMessage").unwrap(),
+            "A message.\nIts second paragraph."
+        );
+    }
+    #[test]
+    fn test_check() {
+        let fixtures = fixtures();
+        let temp_output_dir = std::env::temp_dir().join(rand::random::<u64>().to_string());
+        let check = |fixture: &str| {
+            entry_point(Args {
+                files: vec![fixtures.join(fixture)],
+                includes: vec![fixtures.clone()],
+                output: Some(temp_output_dir.clone()),
+                check: true,
+                ..Default::default()
+            })
+        };
+        let err = check("basic.proto").unwrap_err();
+        assert_eq!(err.to_string(), "1 file(s) still carry description options");
+        check("basic.expected.proto").unwrap();
+        // nothing is written in check mode
+        assert!(!temp_output_dir.exists());
+    }
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff(
+            Path::new("test/basic.proto"),
+            "message A {\n    option (doc.message_description) = \"A.\";\n}\n",
+            "// A.\nmessage A {\n}\n",
+        );
+        assert_eq!(
+            diff,
+            "--- a/test/basic.proto\n+++ b/test/basic.proto\n@@ -1,3 +1,3 @@\n+// A.\n message A {\n-    option (doc.message_description) = \"A.\";\n }\n"
+        );
+        assert_eq!(unified_diff(Path::new("basic.proto"), "same\n", "same\n"), "");
+    }
+    #[test]
+    fn test_in_place() {
+        let fixtures = fixtures();
+        let temp_dir = std::env::temp_dir().join(rand::random::<u64>().to_string());
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        for fixture in ["basic.proto", "doc.proto"] {
+            std::fs::copy(fixtures.join(fixture), temp_dir.join(fixture)).unwrap();
+        }
+        entry_point(Args {
+            files: vec![temp_dir.join("basic.proto")],
+            includes: vec![temp_dir.clone()],
+            in_place: true,
+            backup_suffix: Some(".orig".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(
+            read(temp_dir.join("basic.proto")),
+            read(fixtures.join("basic.expected.proto"))
+        );
+        assert_eq!(
+            read(temp_dir.join("basic.proto.orig")),
+            read(fixtures.join("basic.proto"))
+        );
+        let mut names: Vec<_> = std::fs::read_dir(&temp_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["basic.proto", "basic.proto.orig", "doc.proto"]);
+        std::fs::remove_dir_all(temp_dir).unwrap();
+    }
 }
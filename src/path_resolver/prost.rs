@@ -64,7 +64,11 @@ impl PathedChilds for FileDescriptor {
             }
             tag::file::EXTENSION => {
                 let extension = self.extensions().nth(idx)?;
-                Some(PathedDescriptor::Extension(extension))
+                if path.is_empty() {
+                    Some(PathedDescriptor::Extension(extension))
+                } else {
+                    None
+                }
             }
             tag::file::OPTIONS => get_option_field(self.options(), idx),
             _ => None,
@@ -154,6 +158,14 @@ impl PathedChilds for MessageDescriptor {
                     nested_type.get_child_from_path(path)
                 }
             }
+            tag::message::EXTENSION => {
+                let extension = self.child_extensions().nth(idx)?;
+                if path.is_empty() {
+                    Some(PathedDescriptor::Extension(extension))
+                } else {
+                    None
+                }
+            }
             tag::message::OPTIONS => get_option_field(self.options(), idx),
             tag::message::ONEOF_DECL => {
                 let oneof = self.oneofs().nth(idx)?;
@@ -183,3 +195,73 @@ impl PathedChilds for MessageDescriptor {
         }
     }
 }
+
+/// All the documented elements of `file`, with their path in the source info
+/// Map entries and the oneofs of proto3 `optional` fields are synthetic, so they are skipped.
+pub(crate) fn elements(file: &FileDescriptor) -> Vec<(Vec<i32>, PathedDescriptor)> {
+    let mut elements = Vec::new();
+    for (i, message) in file.messages().enumerate() {
+        message_elements(message, vec![tag::file::MESSAGE_TYPE, i as i32], &mut elements);
+    }
+    for (i, enum_) in file.enums().enumerate() {
+        enum_elements(enum_, vec![tag::file::ENUM_TYPE, i as i32], &mut elements);
+    }
+    for (i, service) in file.services().enumerate() {
+        let path = vec![tag::file::SERVICE, i as i32];
+        for (j, method) in service.methods().enumerate() {
+            let path = [path.as_slice(), &[tag::service::METHOD, j as i32]].concat();
+            elements.push((path, PathedDescriptor::Method(method)));
+        }
+        elements.push((path, PathedDescriptor::Service(service)));
+    }
+    for (i, extension) in file.extensions().enumerate() {
+        elements.push((vec![tag::file::EXTENSION, i as i32], PathedDescriptor::Extension(extension)));
+    }
+    elements
+}
+fn message_elements(
+    message: MessageDescriptor,
+    path: Vec<i32>,
+    elements: &mut Vec<(Vec<i32>, PathedDescriptor)>,
+) {
+    let child = |typ: i32, idx: usize| [path.as_slice(), &[typ, idx as i32]].concat();
+    for (i, field) in message.fields().enumerate() {
+        elements.push((child(tag::message::FIELD, i), PathedDescriptor::Field(field)));
+    }
+    for (i, oneof) in message.oneofs().enumerate() {
+        if is_synthetic(&oneof) {
+            continue;
+        }
+        elements.push((child(tag::message::ONEOF_DECL, i), PathedDescriptor::Oneof(oneof)));
+    }
+    for (i, extension) in message.child_extensions().enumerate() {
+        elements.push((child(tag::message::EXTENSION, i), PathedDescriptor::Extension(extension)));
+    }
+    for (i, enum_) in message.child_enums().enumerate() {
+        enum_elements(enum_, child(tag::message::ENUM_TYPE, i), elements);
+    }
+    for (i, nested) in message.child_messages().enumerate() {
+        if nested.is_map_entry() {
+            continue;
+        }
+        message_elements(nested, child(tag::message::NESTED_TYPE, i), elements);
+    }
+    elements.push((path, PathedDescriptor::Message(message)));
+}
+/// True for the oneof generated for a proto3 `optional` field
+fn is_synthetic(oneof: &OneofDescriptor) -> bool {
+    oneof
+        .fields()
+        .all(|field| field.field_descriptor_proto().proto3_optional())
+}
+fn enum_elements(
+    enum_: EnumDescriptor,
+    path: Vec<i32>,
+    elements: &mut Vec<(Vec<i32>, PathedDescriptor)>,
+) {
+    for (i, value) in enum_.values().enumerate() {
+        let path = [path.as_slice(), &[tag::enum_::VALUE, i as i32]].concat();
+        elements.push((path, PathedDescriptor::EnumValue(value)));
+    }
+    elements.push((path, PathedDescriptor::Enum(enum_)));
+}
@@ -6,6 +6,7 @@ use super::tag;
 
 
 pub(crate) enum PathedDescriptor<'a> {
+    File(&'a mut FileDescriptorProto),
     Message(&'a mut DescriptorProto),
     Enum(&'a mut EnumDescriptorProto),
     Service(&'a mut ServiceDescriptorProto),
@@ -18,16 +19,20 @@ pub(crate) enum PathedDescriptor<'a> {
 
 
 pub(crate) trait PathedChilds {
-    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor>;
-    fn get_child_from_loc(&mut self, loc: &Location) -> Option<PathedDescriptor> {
+    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor<'_>>;
+    fn get_child_from_loc(&mut self, loc: &Location) -> Option<PathedDescriptor<'_>> {
         let mut path: VecDeque<i32> = loc.path.iter().copied().collect();
         self.get_child_from_path(&mut path)
     }
 }
 
 impl PathedChilds for FileDescriptorProto {
-    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor> {
+    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor<'_>> {
         let typ = path.pop_front()?;
+        // the syntax and package statements carry the file documentation
+        if path.is_empty() && (typ == tag::file::SYNTAX || typ == tag::file::PACKAGE) {
+            return Some(PathedDescriptor::File(self));
+        }
         let idx = path.pop_front()? as usize;
         match typ {
             tag::file::MESSAGE_TYPE => {
@@ -56,7 +61,11 @@ impl PathedChilds for FileDescriptorProto {
             }
             tag::file::EXTENSION => {
                 let extension = self.extension.get_mut(idx)?;
-                Some(PathedDescriptor::Extension(extension))
+                if path.is_empty() {
+                    Some(PathedDescriptor::Extension(extension))
+                } else {
+                    None
+                }
             }
             _ => None,
         }
@@ -64,7 +73,7 @@ impl PathedChilds for FileDescriptorProto {
 }
 
 impl PathedChilds for ServiceDescriptorProto {
-    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor> {
+    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor<'_>> {
         let typ = path.pop_front()?;
         let idx = path.pop_front()? as usize;
         match typ {
@@ -82,7 +91,7 @@ impl PathedChilds for ServiceDescriptorProto {
 }
 
 impl PathedChilds for EnumDescriptorProto {
-    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor> {
+    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor<'_>> {
         let typ = path.pop_front()?;
         let idx = path.pop_front()? as usize;
         match typ {
@@ -100,7 +109,7 @@ impl PathedChilds for EnumDescriptorProto {
 }
 
 impl PathedChilds for DescriptorProto {
-    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor> {
+    fn get_child_from_path(&mut self, path: &mut VecDeque<i32>) -> Option<PathedDescriptor<'_>> {
         let typ = path.pop_front()?;
         let idx = path.pop_front()? as usize;
         match typ {
@@ -128,6 +137,14 @@ impl PathedChilds for DescriptorProto {
                     nested_type.get_child_from_path(path)
                 }
             }
+            tag::message::EXTENSION => {
+                let extension = self.extension.get_mut(idx)?;
+                if path.is_empty() {
+                    Some(PathedDescriptor::Extension(extension))
+                } else {
+                    None
+                }
+            }
             tag::message::ONEOF_DECL => {
                 let oneof = self.oneof_decl.get_mut(idx)?;
                 if path.is_empty() {
@@ -0,0 +1,205 @@
+//! References to other elements in documentation text, as `[foo.v1.Bar]` or `` `Bar.baz` ``.
+//!
+//! Backquoted names are only references when they are dotted, as single words are usually code.
+//! References are resolved relative to the documented element, like protoc resolves type names.
+
+use std::{collections::HashMap, ops::Range};
+
+use prost_reflect::DescriptorPool;
+use regex::Regex;
+
+use crate::markdown::{is_fence, is_indented_code};
+use crate::option2comments::describe;
+use crate::path_resolver::prost::elements;
+
+/// A reference in a text
+#[derive(Debug, PartialEq)]
+pub(crate) struct Reference<'a> {
+    /// The range of the name in the text
+    pub range: Range<usize>,
+    pub name: &'a str,
+}
+
+/// The references of `text`, except in fenced or indented code and in Markdown links
+pub(crate) fn references(text: &str) -> Vec<Reference<'_>> {
+    let reference = Regex::new(concat!(
+        r"\[(?<bracket>\.?[A-Za-z_]\w*(?:\.[A-Za-z_]\w*)*)\]",
+        r"|`(?<backquote>\.?[A-Za-z_]\w*(?:\.[A-Za-z_]\w*)+)`"
+    ))
+    .unwrap();
+    let mut references = Vec::new();
+    let mut offset = 0;
+    let mut in_fence = false;
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence || is_indented_code(line) {
+            continue;
+        }
+        for captures in reference.captures_iter(line) {
+            let whole = captures.get(0).unwrap();
+            let name = match (captures.name("bracket"), captures.name("backquote")) {
+                // `[text](url)` and `[text][label]` are links, `[label]` of `[text][label]` too
+                (Some(_), _)
+                    if line[whole.end()..].starts_with(['(', '['])
+                        || line[..whole.start()].ends_with(']') =>
+                {
+                    continue
+                }
+                (Some(name), _) => name,
+                (None, Some(name)) if name.as_str().ends_with(".proto") => continue,
+                (None, Some(name)) => name,
+                (None, None) => continue,
+            };
+            references.push(Reference {
+                range: start + name.start()..start + name.end(),
+                name: name.as_str(),
+            });
+        }
+    }
+    references
+}
+
+/// The full names of the elements and packages of a pool, by the names they go by
+///
+/// Enum values are in the scope of their enum's parent, like in protoc, and are also found
+/// qualified by their enum, e.g. `Color.COLOR_RED` for `COLOR_RED`.
+pub(crate) struct Names(HashMap<String, String>);
+
+impl Names {
+    pub fn new(pool: &DescriptorPool) -> Names {
+        let mut names = HashMap::new();
+        for fd in pool.files() {
+            let mut package = fd.package_name();
+            while !package.is_empty() {
+                names.insert(package.to_string(), package.to_string());
+                package = package.rsplit_once('.').map_or("", |(parent, _)| parent);
+            }
+            for (_, pathed) in elements(&fd).iter() {
+                let name = describe(pathed).1;
+                names.insert(name.clone(), name);
+            }
+        }
+        for enum_ in pool.all_enums() {
+            for value in enum_.values() {
+                let name = format!("{}.{}", enum_.full_name(), value.name());
+                names.insert(name, value.full_name().to_string());
+            }
+        }
+        Names(names)
+    }
+    /// The full name of the element `name` refers to from the element (or package) `scope`
+    ///
+    /// As with protoc, the scopes are searched from the innermost one for the first component of
+    /// `name`, and the rest of `name` must be found in the first scope where it is.
+    pub fn resolve(&self, name: &str, scope: &str) -> Option<String> {
+        if let Some(name) = name.strip_prefix('.') {
+            return self.0.get(name).cloned();
+        }
+        let first = name.split('.').next().unwrap_or(name);
+        let join = |scope: &str, name: &str| {
+            if scope.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", scope, name)
+            }
+        };
+        let mut scope = scope;
+        loop {
+            if self.0.contains_key(&join(scope, first)) {
+                return self.0.get(&join(scope, name)).cloned();
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rsplit_once('.').map_or("", |(parent, _)| parent);
+        }
+    }
+}
+
+/// `text` with its references resolved from `scope` replaced by their full name
+pub(crate) fn qualify(text: &str, scope: &str, names: &Names) -> String {
+    let mut qualified = String::new();
+    let mut end = 0;
+    for reference in references(text) {
+        if let Some(full_name) = names.resolve(reference.name, scope) {
+            qualified.push_str(&text[end..reference.range.start]);
+            qualified.push_str(&full_name);
+            end = reference.range.end;
+        }
+    }
+    qualified.push_str(&text[end..]);
+    qualified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::tests::fixtures::compile;
+
+    fn names() -> Names {
+        Names::new(&compile("references.proto").descriptor_pool())
+    }
+    #[test]
+    fn test_references() {
+        let text = "See [Bar], [.foo.Bar] and `Bar.baz`, not `baz`, [a link](x), [text][Bar],
+`foo.proto` nor `Bar.baz()`.
+```
+[Code]
+```
+    [Code]";
+        let names: Vec<&str> = references(text).iter().map(|r| r.name).collect();
+        assert_eq!(names, vec!["Bar", ".foo.Bar", "Bar.baz"]);
+        let reference = &references(text)[0];
+        assert_eq!(&text[reference.range.clone()], "Bar");
+    }
+    #[test]
+    fn test_resolve() {
+        let names = names();
+        let resolve = |name: &str, scope: &str| names.resolve(name, scope);
+        let item = Some("refs.v1.Item".to_string());
+        assert_eq!(resolve("Item", "refs.v1.Item.name"), item);
+        assert_eq!(resolve("refs.v1.Item", "refs.v1.Catalog.items"), item);
+        assert_eq!(resolve(".refs.v1.Item", ""), item);
+        assert_eq!(resolve("v1.Item", "refs.v1.Catalog"), item);
+        assert_eq!(resolve("Item", "other"), None);
+        // nested elements shadow the outer ones
+        assert_eq!(
+            resolve("Item", "refs.v1.Catalog"),
+            Some("refs.v1.Catalog.Item".to_string())
+        );
+        // enum values are siblings of their enum
+        assert_eq!(
+            resolve("COLOR_RED", "refs.v1.Item.color"),
+            Some("refs.v1.Item.COLOR_RED".to_string())
+        );
+        // and are found by their enum too
+        assert_eq!(
+            resolve("Color.COLOR_RED", "refs.v1.Item"),
+            Some("refs.v1.Item.COLOR_RED".to_string())
+        );
+        assert_eq!(resolve("Color.COLOR_RED", "refs.v1.Catalog.items"), None);
+        // the first scope having `Item` is searched, not the outer ones
+        assert_eq!(
+            resolve("Item.name", "refs.v1.Catalog.Item"),
+            Some("refs.v1.Catalog.Item.name".to_string())
+        );
+        assert_eq!(resolve("Item.color", "refs.v1.Catalog.Item"), None);
+    }
+    #[test]
+    fn test_qualify() {
+        assert_eq!(
+            qualify(
+                "A [Item], `Item.name` and [Missing], see [Item](url).",
+                "refs.v1",
+                &names()
+            ),
+            "A [refs.v1.Item], `refs.v1.Item.name` and [Missing], see [Item](url)."
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! Self-contained HTML pages, with a client-side search and collapsible nested types.
+//!
+//! Every element has an anchor named by its full name. The search index and the script
+//! go to `search.js`, next to the pages, so that the site works offline, from `file://` URLs.
+
+use std::fmt::Write as _;
+
+use prost_reflect::{
+    DynamicMessage, EnumDescriptor, FieldDescriptor, FileDescriptor, Kind, MessageDescriptor,
+};
+use serde::Serialize;
+
+use super::{
+    extensions, is_deprecated, label, page_name, relative_name, services, type_name, Args, Site,
+};
+use crate::error::{Error, Result};
+use crate::markdown::{self, Block};
+
+/// An element of the search index
+#[derive(Debug, Serialize)]
+pub(crate) struct IndexEntry {
+    name: String,
+    kind: &'static str,
+    page: String,
+    /// The first paragraph of the description
+    summary: String,
+}
+
+pub fn entry_point(args: Args) -> Result<()> {
+    let site = Site::load(&args)?;
+    std::fs::create_dir_all(&args.output).map_err(Error::io(&args.output))?;
+    let mut index = Vec::new();
+    let mut pages = vec![("index.html".to_string(), render_index(&site))];
+    for (package, files) in &site.packages {
+        let name = format!("{}.html", page_name(package));
+        pages.push((name, render_package(&site, package, files, &mut index)));
+    }
+    pages.push(("search.js".to_string(), search_script(&index)));
+    for (name, text) in pages {
+        let path = args.output.join(name);
+        std::fs::write(&path, text).map_err(Error::io(&path))?;
+        println!("wrote file: {}", path.to_string_lossy());
+    }
+    Ok(())
+}
+
+const STYLE: &str = r#"body { font-family: sans-serif; margin: 0; display: flex; }
+nav { width: 16rem; padding: 1rem; border-right: 1px solid #ddd; position: sticky; top: 0; height: 100vh; box-sizing: border-box; overflow-y: auto; }
+nav input { width: 100%; box-sizing: border-box; }
+nav ul { padding-left: 1rem; }
+main { padding: 1rem 2rem; flex: 1; min-width: 0; }
+table { border-collapse: collapse; margin: 0.5rem 0; }
+th, td { border: 1px solid #ddd; padding: 0.25rem 0.5rem; text-align: left; vertical-align: top; }
+section { margin: 1rem 0; }
+details { margin: 0.5rem 0 0.5rem 1rem; }
+summary { cursor: pointer; }
+a.anchor { color: #bbb; text-decoration: none; margin-left: 0.25rem; }
+.deprecated { color: #a00; font-size: 0.8em; margin-left: 0.5rem; }
+tr.deprecated-row td:first-child { text-decoration: line-through; }
+:target { background: #ffc; }
+"#;
+
+const SCRIPT: &str = r##"const search = document.getElementById("search");
+const results = document.getElementById("results");
+search.addEventListener("input", () => {
+    const query = search.value.trim().toLowerCase();
+    results.replaceChildren();
+    if (!query) {
+        return;
+    }
+    const matches = SEARCH_INDEX.filter((entry) => entry.name.toLowerCase().includes(query));
+    for (const entry of matches.slice(0, 50)) {
+        const link = document.createElement("a");
+        link.href = entry.page + "#" + entry.name;
+        link.textContent = entry.name;
+        link.title = entry.summary;
+        const item = document.createElement("li");
+        item.append(link, " (" + entry.kind + ")");
+        results.append(item);
+    }
+});
+// nested types are collapsed, open them when they are the target of a link
+function openTarget() {
+    const id = decodeURIComponent(location.hash.slice(1));
+    const target = id && document.getElementById(id);
+    for (let element = target; element; element = element.parentElement) {
+        if (element.tagName === "DETAILS") {
+            element.open = true;
+        }
+    }
+    if (target) {
+        target.scrollIntoView();
+    }
+}
+window.addEventListener("hashchange", openTarget);
+openTarget();
+"##;
+
+/// The search index and the script using it
+pub(crate) fn search_script(index: &[IndexEntry]) -> String {
+    format!(
+        "const SEARCH_INDEX = {};\n{}",
+        serde_json::to_string(index).unwrap(),
+        SCRIPT
+    )
+}
+
+/// A complete page, with the search box
+fn page(title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+{STYLE}</style>
+</head>
+<body>
+<nav>
+<p><a href="index.html">Packages</a></p>
+<input id="search" type="search" placeholder="Search" autocomplete="off">
+<ul id="results"></ul>
+</nav>
+<main>
+{body}</main>
+<script src="search.js"></script>
+</body>
+</html>
+"#,
+        title = escape(title),
+    )
+}
+
+/// The list of the documented packages
+pub(crate) fn render_index(site: &Site) -> String {
+    let mut body = String::from("<h1>Packages</h1>\n<ul>\n");
+    for package in site.packages.keys() {
+        let name = if package.is_empty() { "(default)" } else { package };
+        writeln!(
+            body,
+            "<li><a href=\"{}.html\"><code>{}</code></a></li>",
+            escape(page_name(package)),
+            escape(name)
+        )
+        .unwrap();
+    }
+    body.push_str("</ul>\n");
+    page("Packages", &body)
+}
+
+/// Renders the elements of a package, and adds them to the search index
+struct Renderer<'a> {
+    site: &'a Site,
+    package: &'a str,
+    body: String,
+    index: &'a mut Vec<IndexEntry>,
+}
+
+/// The page documenting `package`, made of `files`, whose elements are added to `index`
+pub(crate) fn render_package(
+    site: &Site,
+    package: &str,
+    files: &[FileDescriptor],
+    index: &mut Vec<IndexEntry>,
+) -> String {
+    let title = if package.is_empty() {
+        "Default package".to_string()
+    } else {
+        format!("Package {}", package)
+    };
+    let mut renderer = Renderer {
+        site,
+        package,
+        body: String::new(),
+        index,
+    };
+    renderer.render(files);
+    page(&title, &renderer.body)
+}
+
+impl Renderer<'_> {
+    fn render(&mut self, files: &[FileDescriptor]) {
+        match self.package {
+            "" => self.body.push_str("<h1>Default package</h1>\n"),
+            package => {
+                writeln!(self.body, "<h1>Package <code>{}</code></h1>", escape(package)).unwrap()
+            }
+        }
+        for fd in files {
+            writeln!(self.body, "<p>File <code>{}</code></p>", escape(fd.name())).unwrap();
+            self.description(fd.name());
+        }
+        let services = services(files);
+        if !services.is_empty() {
+            self.body.push_str("<h2>Services</h2>\n");
+        }
+        for service in services {
+            self.open_section(service.full_name(), "service", &service.options());
+            self.table(&["Method", "Request", "Response", "Description"]);
+            for method in service.methods() {
+                let stream = |streaming: bool| if streaming { "stream " } else { "" };
+                let input = self.type_link(&Kind::Message(method.input()));
+                let output = self.type_link(&Kind::Message(method.output()));
+                self.row(
+                    method.full_name(),
+                    "method",
+                    &method.options(),
+                    &[
+                        format!("{}{}", stream(method.is_client_streaming()), input),
+                        format!("{}{}", stream(method.is_server_streaming()), output),
+                    ],
+                );
+            }
+            self.body.push_str("</table>\n</section>\n");
+        }
+        let messages: Vec<MessageDescriptor> =
+            files.iter().flat_map(|fd| fd.messages()).collect();
+        if !messages.is_empty() {
+            self.body.push_str("<h2>Messages</h2>\n");
+        }
+        for message in messages {
+            self.message(&message);
+        }
+        let enums: Vec<EnumDescriptor> = files.iter().flat_map(|fd| fd.enums()).collect();
+        if !enums.is_empty() {
+            self.body.push_str("<h2>Enums</h2>\n");
+        }
+        for enum_ in enums {
+            self.enum_(&enum_);
+        }
+        let extensions = extensions(files);
+        if !extensions.is_empty() {
+            self.body.push_str("<h2>Extensions</h2>\n");
+            self.table(&["Extension", "Type", "Extendee", "Number", "Description"]);
+        }
+        for extension in &extensions {
+            let extendee = format!(
+                "<code>{}</code>",
+                escape(extension.containing_message().full_name())
+            );
+            self.row(
+                extension.full_name(),
+                "extension",
+                &extension.options(),
+                &[
+                    self.type_link(&extension.kind()),
+                    extendee,
+                    extension.number().to_string(),
+                ],
+            );
+        }
+        if !extensions.is_empty() {
+            self.body.push_str("</table>\n");
+        }
+    }
+
+    /// A message, with its nested messages and enums collapsed
+    fn message(&mut self, message: &MessageDescriptor) {
+        self.open_section(message.full_name(), "message", &message.options());
+        if message.fields().len() > 0 {
+            self.table(&["Field", "Type", "Label", "Number", "Description"]);
+            for field in message.fields() {
+                let cells = [self.field_type(&field), label(&field), field.number().to_string()];
+                self.row(field.full_name(), "field", &field.options(), &cells);
+            }
+            self.body.push_str("</table>\n");
+        }
+        for nested in message.child_messages().filter(|nested| !nested.is_map_entry()) {
+            self.open_details(nested.name());
+            self.message(&nested);
+            self.body.push_str("</details>\n");
+        }
+        for nested in message.child_enums() {
+            self.open_details(nested.name());
+            self.enum_(&nested);
+            self.body.push_str("</details>\n");
+        }
+        self.body.push_str("</section>\n");
+    }
+
+    fn enum_(&mut self, enum_: &EnumDescriptor) {
+        self.open_section(enum_.full_name(), "enum", &enum_.options());
+        self.table(&["Value", "Number", "Description"]);
+        for value in enum_.values() {
+            let cells = [value.number().to_string()];
+            self.row(value.full_name(), "enum value", &value.options(), &cells);
+        }
+        self.body.push_str("</table>\n</section>\n");
+    }
+
+    fn open_details(&mut self, name: &str) {
+        writeln!(self.body, "<details>\n<summary><code>{}</code></summary>", escape(name)).unwrap();
+    }
+
+    /// Open the section of an element, with its heading and description
+    fn open_section(&mut self, full_name: &str, kind: &'static str, options: &DynamicMessage) {
+        self.add_to_index(full_name, kind);
+        let id = escape(full_name);
+        writeln!(self.body, "<section id=\"{}\">", id).unwrap();
+        writeln!(
+            self.body,
+            "<h3><code>{}</code><a class=\"anchor\" href=\"#{}\">#</a>{}</h3>",
+            escape(relative_name(full_name, self.package)),
+            id,
+            deprecated_marker(options)
+        )
+        .unwrap();
+        self.description(full_name);
+    }
+
+    fn table(&mut self, headers: &[&str]) {
+        self.body.push_str("<table>\n<tr>");
+        for header in headers {
+            write!(self.body, "<th>{}</th>", header).unwrap();
+        }
+        self.body.push_str("</tr>\n");
+    }
+
+    /// The row of an element, starting with its name and ending with its description
+    fn row(
+        &mut self,
+        full_name: &str,
+        kind: &'static str,
+        options: &DynamicMessage,
+        cells: &[String],
+    ) {
+        self.add_to_index(full_name, kind);
+        let id = escape(full_name);
+        let class = if is_deprecated(options) {
+            " class=\"deprecated-row\""
+        } else {
+            ""
+        };
+        let name = full_name.rsplit('.').next().unwrap_or(full_name);
+        write!(
+            self.body,
+            "<tr id=\"{}\"{}><td><code>{}</code><a class=\"anchor\" href=\"#{}\">#</a>{}</td>",
+            id,
+            class,
+            escape(name),
+            id,
+            deprecated_marker(options)
+        )
+        .unwrap();
+        for cell in cells {
+            write!(self.body, "<td>{}</td>", cell).unwrap();
+        }
+        let description = self.site.doc(full_name).map(description_html).unwrap_or_default();
+        writeln!(self.body, "<td>{}</td></tr>", description).unwrap();
+    }
+
+    fn description(&mut self, name: &str) {
+        if let Some(doc) = self.site.doc(name) {
+            self.body.push_str(&description_html(doc));
+        }
+    }
+
+    fn add_to_index(&mut self, full_name: &str, kind: &'static str) {
+        let summary = self.site.doc(full_name).unwrap_or_default();
+        self.index.push(IndexEntry {
+            name: full_name.to_string(),
+            kind,
+            page: format!("{}.html", page_name(self.package)),
+            summary: summary.lines().next().unwrap_or_default().to_string(),
+        });
+    }
+
+    /// The type of a field, `map<K, V>` for maps
+    fn field_type(&self, field: &FieldDescriptor) -> String {
+        match field.kind() {
+            Kind::Message(entry) if field.is_map() => format!(
+                "map&lt;{}, {}&gt;",
+                self.type_link(&entry.map_entry_key_field().kind()),
+                self.type_link(&entry.map_entry_value_field().kind())
+            ),
+            kind => self.type_link(&kind),
+        }
+    }
+
+    /// A scalar type, or a link to the documentation of a message or enum type when there is one
+    fn type_link(&self, kind: &Kind) -> String {
+        let Some((package, full_name)) = type_name(kind) else {
+            return format!("<code>{:?}</code>", kind);
+        };
+        match self.site.link(self.package, package, full_name, "html") {
+            Some(target) => format!(
+                "<a href=\"{}\"><code>{}</code></a>",
+                escape(&target),
+                escape(relative_name(full_name, self.package))
+            ),
+            None => format!("<code>{}</code>", escape(full_name)),
+        }
+    }
+}
+
+fn deprecated_marker(options: &DynamicMessage) -> &'static str {
+    if is_deprecated(options) {
+        "<span class=\"deprecated\">deprecated</span>"
+    } else {
+        ""
+    }
+}
+
+/// A description as HTML: paragraphs, lists and preformatted verbatim blocks
+fn description_html(doc: &str) -> String {
+    let mut html = String::new();
+    let mut in_list = false;
+    for block in markdown::blocks(doc) {
+        let is_item = matches!(block, Block::ListItem { .. });
+        if in_list && !is_item {
+            html.push_str("</ul>\n");
+        }
+        if is_item && !in_list {
+            html.push_str("<ul>\n");
+        }
+        in_list = is_item;
+        match block {
+            Block::Paragraph(text) => writeln!(html, "<p>{}</p>", escape(&text)).unwrap(),
+            Block::ListItem { text, .. } => writeln!(html, "<li>{}</li>", escape(&text)).unwrap(),
+            Block::Verbatim(lines) => {
+                // fences are not part of the code
+                let lines: Vec<&String> = lines
+                    .iter()
+                    .filter(|line| !markdown::is_fence(line))
+                    .collect();
+                let text: Vec<String> = lines.iter().map(|line| escape(line)).collect();
+                let text = text.join("\n");
+                writeln!(html, "<pre><code>{}</code></pre>", text).unwrap();
+            }
+        }
+    }
+    if in_list {
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::fixture_site;
+    use pretty_assertions::assert_eq;
+    use crate::tests::fixtures::fixtures;
+
+    #[test]
+    fn test_render_package() {
+        let site = fixture_site("site.proto");
+        let mut index = Vec::new();
+        let page = render_package(&site, "site", &site.packages["site"], &mut index);
+        let expected_path = fixtures().join("site.expected.html");
+        if expected_path.exists() {
+            assert_eq!(std::fs::read_to_string(&expected_path).unwrap(), page);
+        } else {
+            // if it is not present, write expected file from generation
+            std::fs::write(&expected_path, page).unwrap();
+        }
+        let place = index
+            .iter()
+            .find(|entry| entry.name == "site.Thing.Place")
+            .unwrap();
+        assert_eq!(place.kind, "message");
+        assert_eq!(place.page, "site.html");
+        assert_eq!(place.summary, "Where a thing is.");
+    }
+    #[test]
+    fn test_description_html() {
+        assert_eq!(
+            description_html("A <b> paragraph.\n- a\n- b\n```\nlet x;\n```"),
+            "<p>A &lt;b&gt; paragraph.</p>\n<ul>\n<li>a</li>\n<li>b</li>\n</ul>\n<pre><code>let x;</code></pre>\n"
+        );
+    }
+}
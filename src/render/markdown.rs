@@ -0,0 +1,193 @@
+//! Markdown pages, with a table per message, enum and service.
+
+use std::fmt::Write as _;
+
+use prost_reflect::{FieldDescriptor, FileDescriptor, Kind};
+
+use super::{
+    enums, extensions, label, messages, page_name, relative_name, services, type_name, Args, Site,
+};
+use crate::error::{Error, Result};
+use crate::markdown;
+
+pub fn entry_point(args: Args) -> Result<()> {
+    let site = Site::load(&args)?;
+    std::fs::create_dir_all(&args.output).map_err(Error::io(&args.output))?;
+    for (package, files) in &site.packages {
+        let path = args.output.join(format!("{}.md", page_name(package)));
+        std::fs::write(&path, render_package(&site, package, files)).map_err(Error::io(&path))?;
+        println!("wrote file: {}", path.to_string_lossy());
+    }
+    Ok(())
+}
+
+/// The page documenting `package`, made of `files`
+pub(crate) fn render_package(site: &Site, package: &str, files: &[FileDescriptor]) -> String {
+    let mut page = String::new();
+    if package.is_empty() {
+        page.push_str("# Default package\n");
+    } else {
+        writeln!(page, "# Package `{}`", package).unwrap();
+    }
+    for fd in files {
+        writeln!(page, "\nFile `{}`", fd.name()).unwrap();
+        description(&mut page, site.doc(fd.name()));
+    }
+    let link = |kind: &Kind| type_link(site, package, kind);
+    let services = services(files);
+    if !services.is_empty() {
+        page.push_str("\n## Services\n");
+    }
+    for service in services {
+        heading(&mut page, service.full_name(), package);
+        description(&mut page, site.doc(service.full_name()));
+        table(&mut page, &["Method", "Request", "Response", "Description"]);
+        for method in service.methods() {
+            let stream = |streaming: bool| if streaming { "stream " } else { "" };
+            let input = link(&Kind::Message(method.input()));
+            let output = link(&Kind::Message(method.output()));
+            row(
+                &mut page,
+                &[
+                    format!("`{}`", method.name()),
+                    format!("{}{}", stream(method.is_client_streaming()), input),
+                    format!("{}{}", stream(method.is_server_streaming()), output),
+                    cell(site.doc(method.full_name())),
+                ],
+            );
+        }
+    }
+    let messages = messages(files);
+    if !messages.is_empty() {
+        page.push_str("\n## Messages\n");
+    }
+    for message in messages {
+        heading(&mut page, message.full_name(), package);
+        description(&mut page, site.doc(message.full_name()));
+        if message.fields().len() == 0 {
+            continue;
+        }
+        table(&mut page, &["Field", "Type", "Label", "Number", "Description"]);
+        for field in message.fields() {
+            row(
+                &mut page,
+                &[
+                    format!("`{}`", field.name()),
+                    field_type(&field, &link),
+                    label(&field),
+                    field.number().to_string(),
+                    cell(site.doc(field.full_name())),
+                ],
+            );
+        }
+    }
+    let enums = enums(files);
+    if !enums.is_empty() {
+        page.push_str("\n## Enums\n");
+    }
+    for enum_ in enums {
+        heading(&mut page, enum_.full_name(), package);
+        description(&mut page, site.doc(enum_.full_name()));
+        table(&mut page, &["Value", "Number", "Description"]);
+        for value in enum_.values() {
+            row(
+                &mut page,
+                &[
+                    format!("`{}`", value.name()),
+                    value.number().to_string(),
+                    cell(site.doc(value.full_name())),
+                ],
+            );
+        }
+    }
+    let extensions = extensions(files);
+    if !extensions.is_empty() {
+        page.push_str("\n## Extensions\n\n");
+        table(&mut page, &["Extension", "Type", "Extendee", "Number", "Description"]);
+    }
+    for extension in extensions {
+        row(
+            &mut page,
+            &[
+                format!("`{}`", relative_name(extension.full_name(), package)),
+                link(&extension.kind()),
+                format!("`{}`", extension.containing_message().full_name()),
+                extension.number().to_string(),
+                cell(site.doc(extension.full_name())),
+            ],
+        );
+    }
+    page
+}
+
+/// A heading for the element `full_name`, with an anchor for the links to it
+fn heading(page: &mut String, full_name: &str, package: &str) {
+    writeln!(page, "\n<a name=\"{}\"></a>", full_name).unwrap();
+    writeln!(page, "### `{}`", relative_name(full_name, package)).unwrap();
+}
+
+fn description(page: &mut String, doc: Option<&str>) {
+    if let Some(doc) = doc {
+        writeln!(page, "\n{}", markdown::render(doc, usize::MAX).join("\n")).unwrap();
+    }
+}
+
+fn table(page: &mut String, headers: &[&str]) {
+    writeln!(page, "\n| {} |", headers.join(" | ")).unwrap();
+    writeln!(page, "|{}", " --- |".repeat(headers.len())).unwrap();
+}
+
+fn row(page: &mut String, cells: &[String]) {
+    writeln!(page, "| {} |", cells.join(" | ")).unwrap();
+}
+
+/// A description in a table cell: paragraphs are separated by line breaks
+fn cell(doc: Option<&str>) -> String {
+    doc.unwrap_or_default()
+        .replace('|', "\\|")
+        .replace('\n', "<br>")
+}
+
+/// The type of a field, `map<K, V>` for maps
+fn field_type(field: &FieldDescriptor, link: &impl Fn(&Kind) -> String) -> String {
+    match field.kind() {
+        Kind::Message(entry) if field.is_map() => format!(
+            "map<{}, {}>",
+            link(&entry.map_entry_key_field().kind()),
+            link(&entry.map_entry_value_field().kind())
+        ),
+        kind => link(&kind),
+    }
+}
+
+/// A scalar type, or a link to the documentation of a message or enum type when there is one
+fn type_link(site: &Site, current: &str, kind: &Kind) -> String {
+    let Some((package, full_name)) = type_name(kind) else {
+        return format!("`{:?}`", kind);
+    };
+    match site.link(current, package, full_name, "md") {
+        Some(target) => format!("[`{}`]({})", relative_name(full_name, current), target),
+        None => format!("`{}`", full_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::tests::fixture_site;
+    use pretty_assertions::assert_eq;
+    use crate::tests::fixtures::fixtures;
+
+    #[test]
+    fn test_render_package() {
+        let site = fixture_site("site.proto");
+        let page = render_package(&site, "site", &site.packages["site"]);
+        let expected_path = fixtures().join("site.expected.md");
+        if expected_path.exists() {
+            assert_eq!(std::fs::read_to_string(&expected_path).unwrap(), page);
+        } else {
+            // if it is not present, write expected file from generation
+            std::fs::write(&expected_path, page).unwrap();
+        }
+    }
+}
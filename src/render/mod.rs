@@ -0,0 +1,259 @@
+//! Documentation pages generated from a FileDescriptorSet, one per package.
+//!
+//! Descriptions are read from the description options, as selected by a [`DescriptionSelector`].
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use prost_reflect::{
+    Cardinality, DescriptorPool, DynamicMessage, EnumDescriptor, ExtensionDescriptor,
+    FieldDescriptor, FileDescriptor, Kind, MessageDescriptor, ServiceDescriptor, Syntax,
+};
+
+use crate::comments2option::DOC_PROTO;
+use crate::error::{Error, Result};
+use crate::extract::{extract, DocSource, Docs};
+use crate::option2comments::DescriptionSelector;
+
+pub mod html;
+pub mod markdown;
+
+#[derive(Debug, Default, clap::Parser)]
+pub struct Args {
+    /// The encoded FileDescriptorSet to document, e.g. as written by comments2option
+    #[clap(value_name = "DESCRIPTOR_SET", value_parser)]
+    pub(crate) descriptor_set: PathBuf,
+    /// The directory to write the pages to.
+    #[clap(
+        short = 'o',
+        long = "output",
+        value_name = "PATH",
+        default_value = "docs",
+        value_parser
+    )]
+    pub(crate) output: PathBuf,
+    #[clap(flatten)]
+    pub(crate) selector: DescriptionSelector,
+}
+
+/// The documented files of a descriptor set, by package
+pub(crate) struct Site {
+    pub docs: Docs,
+    pub packages: BTreeMap<String, Vec<FileDescriptor>>,
+}
+
+impl Site {
+    /// Read the descriptor set of `args`, and the descriptions of its files
+    /// The files of the `google` packages, e.g. `descriptor.proto`, are not documented,
+    /// nor are the files only declaring options, e.g. the `doc.proto` of comments2option.
+    pub fn load(args: &Args) -> Result<Site> {
+        let path = &args.descriptor_set;
+        let bytes = std::fs::read(path).map_err(Error::io(path))?;
+        let pool = DescriptorPool::decode(bytes.as_slice()).map_err(Error::InvalidDescriptors)?;
+        Site::new(&pool, &args.selector)
+    }
+    pub fn new(pool: &DescriptorPool, selector: &DescriptionSelector) -> Result<Site> {
+        let mut docs = Docs::new();
+        let mut packages: BTreeMap<String, Vec<FileDescriptor>> = BTreeMap::new();
+        for fd in pool.files() {
+            if fd.name().starts_with("google/") || fd.name() == DOC_PROTO || only_options(&fd) {
+                continue;
+            }
+            extract(&fd, DocSource::Options, selector, &mut docs)?;
+            packages
+                .entry(fd.package_name().to_string())
+                .or_default()
+                .push(fd);
+        }
+        Ok(Site { docs, packages })
+    }
+    /// The description of the element (or file) `name`
+    pub fn doc(&self, name: &str) -> Option<&str> {
+        self.docs.get(name).map(|entry| entry.doc.as_str())
+    }
+    /// Where the definition of the type `name` of `package` is documented, if it is,
+    /// from the page of `current` when pages have the file `extension`
+    pub fn link(
+        &self,
+        current: &str,
+        package: &str,
+        name: &str,
+        extension: &str,
+    ) -> Option<String> {
+        if !self.packages.contains_key(package) {
+            return None;
+        }
+        if package == current {
+            Some(format!("#{}", name))
+        } else {
+            Some(format!("{}.{}#{}", page_name(package), extension, name))
+        }
+    }
+}
+
+/// True when `fd` declares nothing but extensions of the options messages
+fn only_options(fd: &FileDescriptor) -> bool {
+    let is_option = |extension: ExtensionDescriptor| {
+        let extendee = extension.containing_message();
+        extendee.package_name() == "google.protobuf" && extendee.name().ends_with("Options")
+    };
+    fd.messages().len() == 0
+        && fd.enums().len() == 0
+        && fd.services().len() == 0
+        && fd.extensions().len() > 0
+        && fd.extensions().all(is_option)
+}
+
+/// The name of the page of `package`, without extension
+pub(crate) fn page_name(package: &str) -> &str {
+    if package.is_empty() {
+        "default"
+    } else {
+        package
+    }
+}
+
+/// The name of `full_name` relative to its package
+pub(crate) fn relative_name<'a>(full_name: &'a str, package: &str) -> &'a str {
+    if package.is_empty() {
+        return full_name;
+    }
+    full_name
+        .strip_prefix(package)
+        .and_then(|name| name.strip_prefix('.'))
+        .unwrap_or(full_name)
+}
+
+/// The messages of `files`, nested ones included, except map entries
+pub(crate) fn messages(files: &[FileDescriptor]) -> Vec<MessageDescriptor> {
+    fn add(message: MessageDescriptor, messages: &mut Vec<MessageDescriptor>) {
+        if message.is_map_entry() {
+            return;
+        }
+        messages.push(message.clone());
+        for child in message.child_messages() {
+            add(child, messages);
+        }
+    }
+    let mut messages = Vec::new();
+    for message in files.iter().flat_map(|fd| fd.messages()) {
+        add(message, &mut messages);
+    }
+    messages
+}
+
+/// The enums of `files`, nested ones included
+pub(crate) fn enums(files: &[FileDescriptor]) -> Vec<EnumDescriptor> {
+    let nested = messages(files)
+        .into_iter()
+        .flat_map(|message| message.child_enums().collect::<Vec<_>>());
+    files.iter().flat_map(|fd| fd.enums()).chain(nested).collect()
+}
+
+pub(crate) fn services(files: &[FileDescriptor]) -> Vec<ServiceDescriptor> {
+    files.iter().flat_map(|fd| fd.services()).collect()
+}
+
+/// The extensions declared in `files`, nested ones included
+pub(crate) fn extensions(files: &[FileDescriptor]) -> Vec<ExtensionDescriptor> {
+    let nested = messages(files)
+        .into_iter()
+        .flat_map(|message| message.child_extensions().collect::<Vec<_>>());
+    files.iter().flat_map(|fd| fd.extensions()).chain(nested).collect()
+}
+
+/// The label of a field: `repeated`, `required`, `optional` when presence is explicit,
+/// `oneof <name>` for the members of a oneof, and nothing otherwise
+pub(crate) fn label(field: &FieldDescriptor) -> String {
+    if field.is_map() {
+        return String::new();
+    }
+    if field.field_descriptor_proto().proto3_optional() {
+        return "optional".to_string();
+    }
+    if let Some(oneof) = field.containing_oneof() {
+        return format!("oneof {}", oneof.name());
+    }
+    match field.cardinality() {
+        Cardinality::Repeated => "repeated".to_string(),
+        Cardinality::Required => "required".to_string(),
+        Cardinality::Optional if field.parent_file().syntax() == Syntax::Proto2 => {
+            "optional".to_string()
+        }
+        Cardinality::Optional => String::new(),
+    }
+}
+
+/// The package and full name of the message or enum type of `kind`
+pub(crate) fn type_name(kind: &Kind) -> Option<(&str, &str)> {
+    match kind {
+        Kind::Message(message) => Some((message.package_name(), message.full_name())),
+        Kind::Enum(enum_) => Some((enum_.package_name(), enum_.full_name())),
+        _ => None,
+    }
+}
+
+/// True when the `deprecated` option is set in `options`
+pub(crate) fn is_deprecated(options: &DynamicMessage) -> bool {
+    options
+        .get_field_by_name("deprecated")
+        .is_some_and(|value| value.as_bool() == Some(true))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::comments2option::{comments2option, Config, DescriptionIds};
+    use crate::tests::fixtures::{compile, test_ids};
+
+    /// The site of a commented fixture, after comments2option
+    pub(crate) fn fixture_site(fixture: &str) -> Site {
+        let pool = fixture_pool(fixture, true);
+        Site::new(&pool, &DescriptionSelector::default()).unwrap()
+    }
+    /// The descriptor set of a commented fixture, after comments2option
+    fn fixture_pool(fixture: &str, embed_doc_proto: bool) -> DescriptorPool {
+        let c = compile(fixture);
+        let config = Config {
+            ids: test_ids(),
+            embed_doc_proto,
+            ..Default::default()
+        };
+        let res = comments2option(&c.encode_file_descriptor_set(), &config).unwrap();
+        DescriptorPool::decode(res.as_slice()).unwrap()
+    }
+    #[test]
+    fn test_skipped_files() {
+        let site = fixture_site("site.proto");
+        let packages: Vec<&str> = site.packages.keys().map(String::as_str).collect();
+        assert_eq!(packages, vec!["other", "site"]);
+        // a doc.proto under another name is skipped as well
+        let pool = fixture_pool("site.proto", true);
+        assert!(only_options(&pool.get_file_by_name(DOC_PROTO).unwrap()));
+        assert!(!only_options(&pool.get_file_by_name("site.proto").unwrap()));
+    }
+    #[test]
+    fn test_undeclared_extensions() {
+        // without `--embed-doc-proto`, the descriptions are unknown fields of the options
+        let pool = fixture_pool("site.proto", false);
+        let site = Site::new(&pool, &DescriptionSelector::default()).unwrap();
+        assert_eq!(site.doc("site.Thing"), None);
+        let id = Some(1000);
+        let selector = DescriptionSelector {
+            names: Vec::new(),
+            ids: DescriptionIds {
+                message: id,
+                enum_: id,
+                enum_value: id,
+                ..Default::default()
+            },
+        };
+        let site = Site::new(&pool, &selector).unwrap();
+        assert_eq!(
+            site.doc("site.Thing"),
+            Some("A thing.\nIt has a second paragraph.")
+        );
+        assert_eq!(site.doc("site.Kind"), Some("The kinds of things."));
+        assert_eq!(site.doc("site.KIND_UNSPECIFIED"), Some("Not known."));
+        assert_eq!(site.doc("site.Thing.kind"), None);
+    }
+}
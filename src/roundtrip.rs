@@ -0,0 +1,587 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::comments2option::{comments2option, Config, DescriptionIds};
+use crate::error::{Error, Result};
+use crate::option2comments::{self, unknown_values, DescriptionSelector};
+use protobuf::{
+    descriptor::{
+        DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+        FileDescriptorSet, ServiceDescriptorProto,
+    },
+    Message, UnknownFields,
+};
+use protox::Compiler;
+
+#[derive(Debug, clap::Parser)]
+pub struct Args {
+    /// The source file(s) to verify
+    #[clap(value_name = "PROTO_FILES", required = true, value_parser)]
+    files: Vec<PathBuf>,
+    /// The directory in which to search for imports.
+    #[clap(
+        short = 'I',
+        long = "include",
+        visible_alias = "proto_path_glob",
+        value_name = "PATH_GLOB",
+        default_value = ".",
+        value_parser
+    )]
+    includes: Vec<PathBuf>,
+    /// The directory to keep the converted files in.
+    /// A temporary directory is used and removed afterwards when not given.
+    #[clap(
+        short = 'o',
+        long = "output",
+        visible_alias = "output_dir",
+        value_name = "PATH",
+        value_parser
+    )]
+    output: Option<PathBuf>,
+    #[clap(flatten)]
+    ids: DescriptionIds,
+}
+
+/// Runs option2comments on the given files, converts the result back with comments2option
+/// and checks that the resulting descriptors match the original ones, apart from source info.
+pub fn entry_point(args: Args) -> Result<()> {
+    if args.ids.is_empty() {
+        return Err(Error::NoDescriptionIds);
+    }
+    let first_include = args
+        .includes
+        .first()
+        .expect("at least one include dir is expected")
+        .clone();
+    let files = expand_globs(&args.files)?;
+    let out_dir = match args.output {
+        Some(output) => OutDir {
+            path: output,
+            keep: true,
+        },
+        None => OutDir {
+            path: temp_dir(),
+            keep: false,
+        },
+    };
+    let (original, names) = compile(args.includes.clone(), &files, false)?;
+    option2comments::entry_point(option2comments::Args {
+        files: files.clone(),
+        includes: args.includes.clone(),
+        output: Some(out_dir.path.clone()),
+        selector: DescriptionSelector {
+            names: Vec::new(),
+            ids: args.ids.clone(),
+        },
+        ..Default::default()
+    })?;
+    let converted_files = files
+        .iter()
+        .map(|file| {
+            let relative = file
+                .strip_prefix(&first_include)
+                .map_err(|_| Error::NotIncluded {
+                    file: file.clone(),
+                    include: first_include.clone(),
+                })?;
+            Ok(out_dir.path.join(relative))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // converted files shadow the original ones, other imports are still found in the includes
+    let mut includes = vec![out_dir.path.clone()];
+    includes.extend(args.includes);
+    let (converted, _) = compile(includes, &converted_files, true)?;
+    drop(out_dir);
+    let config = Config {
+        ids: args.ids.clone(),
+        ..Default::default()
+    };
+    let converted = comments2option(&converted, &config)?;
+    let report = compare(&original, &converted, &args.ids, &names)?;
+    report.print();
+    if !report.drifts.is_empty() {
+        Err(Error::Drifted {
+            count: report.drifts.len(),
+        })
+    } else if !report.mismatched_files.is_empty() {
+        Err(Error::RoundtripMismatch {
+            files: report.mismatched_files,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn expand_globs(globs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for file_glob in globs {
+        let file_glob = file_glob.to_string_lossy();
+        let file_glob = shellexpand::tilde(&file_glob);
+        let paths = glob::glob(&file_glob).map_err(|source| Error::Pattern {
+            pattern: file_glob.to_string(),
+            source,
+        })?;
+        for file in paths {
+            files.push(file.map_err(|err| Error::Io {
+                path: err.path().to_path_buf(),
+                source: err.into_error(),
+            })?);
+        }
+    }
+    Ok(files)
+}
+
+/// The directory of the converted files, removed when dropped unless it is kept
+struct OutDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl Drop for OutDir {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+fn temp_dir() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    std::env::temp_dir().join(format!(
+        "protox-doc-roundtrip-{}-{}",
+        std::process::id(),
+        nanos
+    ))
+}
+
+/// Compile `files`, returns the encoded FileDescriptorSet and the names of the files
+fn compile(
+    includes: Vec<PathBuf>,
+    files: &[PathBuf],
+    source_info: bool,
+) -> Result<(Vec<u8>, Vec<String>)> {
+    let mut compiler = Compiler::new(includes)?;
+    compiler.include_imports(true);
+    compiler.include_source_info(source_info);
+    for file in files {
+        compiler.open_file(file)?;
+    }
+    let names = compiler
+        .files()
+        .filter(|file| !file.is_import())
+        .map(|file| file.name().to_string())
+        .collect();
+    Ok((compiler.encode_file_descriptor_set(), names))
+}
+
+/// A description that did not survive the round-trip unchanged
+#[derive(Debug, PartialEq)]
+pub struct Drift {
+    pub file: String,
+    pub element: String,
+    pub kind: &'static str,
+    pub original: Option<String>,
+    pub roundtripped: Option<String>,
+}
+
+impl Drift {
+    /// A short human readable explanation of the difference
+    pub fn cause(&self) -> &'static str {
+        match (&self.original, &self.roundtripped) {
+            (None, _) => "description appeared",
+            (_, None) => "description lost",
+            (Some(original), Some(roundtripped)) => {
+                let words = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+                if words(original) != words(roundtripped) {
+                    "text changed"
+                } else if original.lines().count() != roundtripped.lines().count() {
+                    "paragraph breaks changed"
+                } else {
+                    "whitespace collapsed"
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub files: usize,
+    pub descriptions: usize,
+    pub drifts: Vec<Drift>,
+    /// Files that differ outside of their descriptions, or are missing after conversion
+    pub mismatched_files: Vec<String>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.drifts.is_empty() && self.mismatched_files.is_empty()
+    }
+    fn print(&self) {
+        for drift in &self.drifts {
+            println!(
+                "{}: {} {} drifted ({})",
+                drift.file,
+                drift.kind,
+                drift.element,
+                drift.cause()
+            );
+            println!("    original:     {:?}", drift.original);
+            println!("    roundtripped: {:?}", drift.roundtripped);
+        }
+        for file in &self.mismatched_files {
+            println!("{}: file differs outside of descriptions", file);
+        }
+        println!(
+            "checked {} descriptions in {} files: {} drifted, {} files differ",
+            self.descriptions,
+            self.files,
+            self.drifts.len(),
+            self.mismatched_files.len()
+        );
+    }
+}
+
+/// Compare the files named `names` of two encoded FileDescriptorSets, ignoring source info
+pub fn compare(
+    original: &[u8],
+    roundtripped: &[u8],
+    ids: &DescriptionIds,
+    names: &[String],
+) -> Result<Report> {
+    let original = FileDescriptorSet::parse_from_bytes(original).map_err(Error::Decode)?;
+    let roundtripped = FileDescriptorSet::parse_from_bytes(roundtripped).map_err(Error::Decode)?;
+    let mut report = Report::default();
+    for file in &original.file {
+        if !names.iter().any(|name| name == file.name()) {
+            continue;
+        }
+        report.files += 1;
+        let Some(other) = roundtripped.file.iter().find(|f| f.name() == file.name()) else {
+            report.mismatched_files.push(file.name().to_string());
+            continue;
+        };
+        let before = descriptions(file, ids);
+        let after = descriptions(other, ids);
+        report.descriptions += before.len();
+        let drifts = report.drifts.len();
+        let mut elements: Vec<_> = before.keys().chain(after.keys()).collect();
+        elements.sort();
+        elements.dedup();
+        for element in elements {
+            let original = before.get(element);
+            let roundtripped = after.get(element);
+            if original.map(|d| &d.1) != roundtripped.map(|d| &d.1) {
+                report.drifts.push(Drift {
+                    file: file.name().to_string(),
+                    element: element.clone(),
+                    kind: original.or(roundtripped).unwrap().0,
+                    original: original.map(|d| d.1.clone()),
+                    roundtripped: roundtripped.map(|d| d.1.clone()),
+                });
+            }
+        }
+        if drifts == report.drifts.len() && !same_file(file, other) {
+            report.mismatched_files.push(file.name().to_string());
+        }
+    }
+    Ok(report)
+}
+
+fn same_file(original: &FileDescriptorProto, roundtripped: &FileDescriptorProto) -> bool {
+    let mut original = original.clone();
+    let mut roundtripped = roundtripped.clone();
+    original.source_code_info.clear();
+    roundtripped.source_code_info.clear();
+    original == roundtripped
+}
+
+type Descriptions = BTreeMap<String, (&'static str, String)>;
+
+/// The description of `id` in `unknown`, the values of a repeated description being paragraphs
+fn description(unknown: &UnknownFields, id: Option<u32>) -> Option<String> {
+    let values = unknown_values(unknown, id?);
+    if values.is_empty() {
+        return None;
+    }
+    let paragraphs: Vec<String> = values
+        .iter()
+        .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+        .collect();
+    Some(paragraphs.join("\n"))
+}
+
+fn join(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+fn descriptions(file: &FileDescriptorProto, ids: &DescriptionIds) -> Descriptions {
+    let mut res = Descriptions::new();
+    let mut add = |name: String, kind: &'static str, desc: Option<String>| {
+        if let Some(desc) = desc {
+            res.insert(name, (kind, desc));
+        }
+    };
+    add(
+        file.name().to_string(),
+        "file",
+        description(file.options.special_fields.unknown_fields(), ids.file),
+    );
+    let scope = file.package();
+    for message in &file.message_type {
+        message_descriptions(message, scope, ids, &mut add);
+    }
+    for enum_ in &file.enum_type {
+        enum_descriptions(enum_, scope, ids, &mut add);
+    }
+    for service in &file.service {
+        service_descriptions(service, scope, ids, &mut add);
+    }
+    for extension in &file.extension {
+        extension_description(extension, scope, ids, &mut add);
+    }
+    res
+}
+
+type Add<'a> = dyn FnMut(String, &'static str, Option<String>) + 'a;
+
+fn message_descriptions(message: &DescriptorProto, scope: &str, ids: &DescriptionIds, add: &mut Add) {
+    let name = join(scope, message.name());
+    add(
+        name.clone(),
+        "message",
+        description(message.options.special_fields.unknown_fields(), ids.message),
+    );
+    for field in &message.field {
+        add(
+            join(&name, field.name()),
+            "field",
+            description(field.options.special_fields.unknown_fields(), ids.field),
+        );
+    }
+    for oneof in &message.oneof_decl {
+        add(
+            join(&name, oneof.name()),
+            "oneof",
+            description(oneof.options.special_fields.unknown_fields(), ids.oneof),
+        );
+    }
+    for nested in &message.nested_type {
+        message_descriptions(nested, &name, ids, add);
+    }
+    for enum_ in &message.enum_type {
+        enum_descriptions(enum_, &name, ids, add);
+    }
+    for extension in &message.extension {
+        extension_description(extension, &name, ids, add);
+    }
+}
+
+fn enum_descriptions(enum_: &EnumDescriptorProto, scope: &str, ids: &DescriptionIds, add: &mut Add) {
+    let name = join(scope, enum_.name());
+    add(
+        name.clone(),
+        "enum",
+        description(enum_.options.special_fields.unknown_fields(), ids.enum_),
+    );
+    for value in &enum_.value {
+        add(
+            join(&name, value.name()),
+            "enum value",
+            description(value.options.special_fields.unknown_fields(), ids.enum_value),
+        );
+    }
+}
+
+fn service_descriptions(
+    service: &ServiceDescriptorProto,
+    scope: &str,
+    ids: &DescriptionIds,
+    add: &mut Add,
+) {
+    let name = join(scope, service.name());
+    add(
+        name.clone(),
+        "service",
+        description(service.options.special_fields.unknown_fields(), ids.service),
+    );
+    for method in &service.method {
+        add(
+            join(&name, method.name()),
+            "method",
+            description(method.options.special_fields.unknown_fields(), ids.method),
+        );
+    }
+}
+
+fn extension_description(
+    extension: &FieldDescriptorProto,
+    scope: &str,
+    ids: &DescriptionIds,
+    add: &mut Add,
+) {
+    add(
+        join(scope, extension.name()),
+        "extension",
+        description(extension.options.special_fields.unknown_fields(), ids.extension),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::{fixtures, test_ids};
+
+    fn roundtrip_test(fixture: &str) {
+        roundtrip_test_ids(fixture, test_ids());
+    }
+    fn roundtrip_test_ids(fixture: &str, ids: DescriptionIds) {
+        let fixtures = fixtures();
+        let args = Args {
+            files: vec![fixtures.join(fixture)],
+            includes: vec![fixtures.clone()],
+            output: None,
+            ids,
+        };
+        entry_point(args).unwrap();
+    }
+    #[test]
+    fn test_basic() {
+        roundtrip_test("basic.proto");
+    }
+    #[test]
+    fn test_nested() {
+        roundtrip_test("nested.proto");
+    }
+    #[test]
+    fn test_siblings() {
+        roundtrip_test("siblings.proto");
+    }
+    #[test]
+    fn test_multiline() {
+        // without file id, see `comments2option::tests::test_multiline`
+        roundtrip_test_ids(
+            "multiline.proto",
+            DescriptionIds {
+                file: None,
+                ..test_ids()
+            },
+        );
+    }
+    #[test]
+    fn test_markdown() {
+        roundtrip_test("markdown.proto");
+    }
+    #[test]
+    fn test_oneof_extension() {
+        roundtrip_test("oneof_extension.proto");
+    }
+    #[test]
+    fn test_file() {
+        roundtrip_test("file.proto");
+    }
+    #[test]
+    fn test_syntax_comment() {
+        roundtrip_test("syntax_comment.proto");
+    }
+
+    /// A descriptor set with a message having a value of its description per paragraph,
+    /// along with an import whose message has no description
+    fn fds_paragraphs(paragraphs: &[&str]) -> Vec<u8> {
+        let mut message = DescriptorProto::new();
+        message.set_name("MyMessage".to_string());
+        let unknown = message
+            .options
+            .mut_or_insert_default()
+            .special_fields
+            .mut_unknown_fields();
+        for paragraph in paragraphs {
+            unknown.add_length_delimited(1000, paragraph.as_bytes().to_vec());
+        }
+        let mut file = FileDescriptorProto::new();
+        file.set_name("test.proto".to_string());
+        file.set_package("test".to_string());
+        file.message_type.push(message);
+        let mut import = FileDescriptorProto::new();
+        import.set_name("import.proto".to_string());
+        let mut set = FileDescriptorSet::new();
+        set.file.push(import);
+        set.file.push(file);
+        set.write_to_bytes().unwrap()
+    }
+    fn fds(description: &str) -> Vec<u8> {
+        fds_paragraphs(&[description])
+    }
+    fn compare_test(original: &[u8], roundtripped: &[u8]) -> Report {
+        compare(original, roundtripped, &test_ids(), &["test.proto".to_string()]).unwrap()
+    }
+    #[test]
+    fn test_drift_report() {
+        let original = fds("First paragraph.\nSecond  paragraph.");
+        let report = compare_test(&original, &original);
+        assert!(report.is_ok());
+        assert_eq!(report.descriptions, 1);
+        // only the given files are checked, not their imports
+        assert_eq!(report.files, 1);
+
+        let report = compare_test(&original, &fds("First paragraph. Second paragraph."));
+        assert_eq!(report.drifts.len(), 1);
+        assert_eq!(report.drifts[0].element, "test.MyMessage");
+        assert_eq!(report.drifts[0].cause(), "paragraph breaks changed");
+
+        let report = compare_test(&original, &fds("First paragraph.\nSecond paragraph."));
+        assert_eq!(report.drifts[0].cause(), "whitespace collapsed");
+
+        let report = compare_test(&original, &fds("First paragraph."));
+        assert_eq!(report.drifts[0].cause(), "text changed");
+        assert!(report.mismatched_files.is_empty());
+    }
+    #[test]
+    fn test_repeated_drift() {
+        let original = fds_paragraphs(&["First paragraph.", "Second paragraph."]);
+        assert!(compare_test(&original, &original).is_ok());
+        let report = compare_test(
+            &original,
+            &fds_paragraphs(&["First  paragraph.", "Second paragraph."]),
+        );
+        assert_eq!(report.drifts.len(), 1);
+        assert_eq!(report.drifts[0].cause(), "whitespace collapsed");
+    }
+    #[test]
+    fn test_no_ids() {
+        let fixtures = fixtures();
+        let args = Args {
+            files: vec![fixtures.join("basic.proto")],
+            includes: vec![fixtures],
+            output: None,
+            ids: DescriptionIds::default(),
+        };
+        let err = entry_point(args).unwrap_err();
+        assert!(matches!(err, Error::NoDescriptionIds));
+    }
+    #[test]
+    fn test_out_dir_removed() {
+        let path = temp_dir();
+        std::fs::create_dir_all(&path).unwrap();
+        drop(OutDir {
+            path: path.clone(),
+            keep: true,
+        });
+        assert!(path.exists());
+        drop(OutDir {
+            path: path.clone(),
+            keep: false,
+        });
+        assert!(!path.exists());
+    }
+}
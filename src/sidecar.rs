@@ -0,0 +1,98 @@
+//! Documentation kept next to the schema, in a JSON or YAML sidecar file.
+//!
+//! A sidecar maps the full names of elements (files by their name) to their description,
+//! either directly or as the `doc` of an entry written by `extract`.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+
+/// The descriptions of a sidecar file, by full name
+pub type Sidecar = BTreeMap<String, String>;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Text(String),
+    Extracted { doc: String },
+}
+
+/// Read a sidecar file, as JSON when its extension is `.json` and as YAML otherwise
+pub fn load(path: &Path) -> Result<Sidecar> {
+    let text = std::fs::read_to_string(path).map_err(Error::io(path))?;
+    let invalid = |message: String| Error::InvalidSidecar {
+        path: path.to_path_buf(),
+        message,
+    };
+    let entries: BTreeMap<String, Entry> = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&text).map_err(|err| invalid(err.to_string()))?
+    } else {
+        serde_yaml::from_str(&text).map_err(|err| invalid(err.to_string()))?
+    };
+    Ok(entries
+        .into_iter()
+        .map(|(name, entry)| match entry {
+            Entry::Text(doc) | Entry::Extracted { doc } => (name, doc),
+        })
+        .collect())
+}
+
+/// Fail on the names of `sidecar` which are not among `known`, suggesting the closest known names
+pub(crate) fn check_names(sidecar: &Sidecar, known: &[String], path: &Path) -> Result<()> {
+    let names: Vec<String> = sidecar
+        .keys()
+        .filter(|name| !known.contains(name))
+        .map(|name| match suggestion(name, known) {
+            Some(suggestion) => format!("{} (did you mean {}?)", name, suggestion),
+            None => name.clone(),
+        })
+        .collect();
+    if names.is_empty() {
+        return Ok(());
+    }
+    Err(Error::UnknownNames {
+        path: path.to_path_buf(),
+        names,
+    })
+}
+
+/// The known name closest to `name`, if close enough
+fn suggestion<'a>(name: &str, known: &'a [String]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|candidate| (strsim::jaro_winkler(name, candidate), candidate))
+        .filter(|(similarity, _)| *similarity > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::tests::fixtures::fixtures;
+
+    #[test]
+    fn test_load() {
+        let path = fixtures().join("sidecar.yaml");
+        let sidecar = load(&path).unwrap();
+        assert_eq!(sidecar["test.MyMessage"], "This is a message documentation.");
+        assert_eq!(sidecar["test.MyMessage.my_field"], "This is a field documentation.");
+    }
+    #[test]
+    fn test_check_names() {
+        let known = vec!["test.MyMessage".to_string(), "test.MyEnum".to_string()];
+        let sidecar = Sidecar::from([
+            ("test.MyMesage".to_string(), String::new()),
+            ("test.MyEnum".to_string(), String::new()),
+            ("other.Thing".to_string(), String::new()),
+        ]);
+        let err = check_names(&sidecar, &known, Path::new("docs.yaml")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown element(s) in docs.yaml: other.Thing, test.MyMesage (did you mean test.MyMessage?)"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Javadoc-like tags in comments, for structured documentation options.
+//!
+//! A line starting with `@deprecated`, `@example`, `@since` or `@see` starts a tag, which goes on
+//! until the next tag. The text before the first tag is the summary (its first paragraph) and the body.
+
+use prost_reflect::{DynamicMessage, Kind, MessageDescriptor, ReflectMessage, Value};
+
+use crate::comments2option::process_markdown_like_whitespace;
+use crate::markdown;
+
+/// The documentation of an element, split into its parts
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Doc {
+    pub summary: String,
+    pub body: String,
+    /// The deprecation reason, possibly empty
+    pub deprecated: Option<String>,
+    pub examples: Vec<String>,
+    pub since: Option<String>,
+    pub see: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tag {
+    Deprecated,
+    Example,
+    Since,
+    See,
+}
+
+/// The tag starting `line` if any, and the rest of the line
+fn tag_of(line: &str) -> Option<(Tag, &str)> {
+    let rest = line.strip_prefix('@')?;
+    let (name, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let tag = match name {
+        "deprecated" => Tag::Deprecated,
+        "example" => Tag::Example,
+        "since" => Tag::Since,
+        "see" => Tag::See,
+        _ => return None,
+    };
+    Some((tag, rest.trim()))
+}
+
+/// Parse the tags out of a comment, as given by protox without its comment markers
+pub(crate) fn parse(comment: &str) -> Doc {
+    let mut doc = Doc::default();
+    let mut description = Vec::new();
+    let mut current: Option<(Tag, Vec<&str>)> = None;
+    let mut in_fence = false;
+    for line in comment.lines() {
+        // comments usually start with a space after the `//`
+        let stripped = line.strip_prefix(' ').unwrap_or(line);
+        if !in_fence {
+            if let Some((tag, rest)) = tag_of(stripped) {
+                doc.add(current.take());
+                current = Some((tag, vec![rest]));
+                continue;
+            }
+        }
+        if markdown::is_fence(stripped) {
+            in_fence = !in_fence;
+        }
+        match current.as_mut() {
+            Some((_, lines)) => lines.push(stripped),
+            None => description.push(line),
+        }
+    }
+    doc.add(current);
+    let description = process_markdown_like_whitespace(&description.join("\n"));
+    let description = description.trim();
+    let (summary, body) = description.split_once('\n').unwrap_or((description, ""));
+    doc.summary = summary.to_string();
+    doc.body = body.trim().to_string();
+    doc
+}
+
+impl Doc {
+    fn add(&mut self, tag: Option<(Tag, Vec<&str>)>) {
+        let Some((tag, lines)) = tag else {
+            return;
+        };
+        match tag {
+            Tag::Example => self.examples.push(dedent(&lines)),
+            Tag::Deprecated => self.deprecated = Some(prose(&lines)),
+            Tag::Since => self.since = Some(prose(&lines)),
+            Tag::See => self.see.push(prose(&lines)),
+        }
+    }
+}
+
+fn prose(lines: &[&str]) -> String {
+    process_markdown_like_whitespace(&lines.join("\n"))
+        .trim()
+        .to_string()
+}
+
+/// Examples are kept verbatim, without their common indentation and surrounding blank lines
+fn dedent(lines: &[&str]) -> String {
+    let start = lines.iter().position(|line| !line.trim().is_empty());
+    let end = lines.iter().rposition(|line| !line.trim().is_empty());
+    let (Some(start), Some(end)) = (start, end) else {
+        return String::new();
+    };
+    let lines = &lines[start..=end];
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Why a part of a doc cannot be stored in the doc message
+#[derive(Debug, PartialEq)]
+pub(crate) enum FieldError {
+    /// No field goes by the name of the part
+    Missing(&'static str),
+    /// The field of the part, e.g. `since` for `@since`, cannot hold text
+    NotText { part: &'static str, field: String },
+}
+
+/// Encode `doc` as a `desc` message, whose fields are found by name:
+/// `summary`, `body`, `deprecated`, `examples` (or `example`), `since` and `see` (or `see_also`).
+/// Fails when a part of `doc` has no `string` field to go to.
+pub(crate) fn to_message(
+    doc: &Doc,
+    desc: &MessageDescriptor,
+) -> Result<DynamicMessage, FieldError> {
+    let mut message = DynamicMessage::new(desc.clone());
+    set(
+        &mut message,
+        "the summary",
+        &["summary"],
+        vec![doc.summary.clone()],
+    )?;
+    set(&mut message, "the body", &["body"], vec![doc.body.clone()])?;
+    if let Some(reason) = &doc.deprecated {
+        // an empty string would not be encoded
+        let reason = if reason.is_empty() {
+            "deprecated"
+        } else {
+            reason
+        };
+        set(
+            &mut message,
+            "@deprecated",
+            &["deprecated"],
+            vec![reason.to_string()],
+        )?;
+    }
+    set(
+        &mut message,
+        "@example",
+        &["examples", "example"],
+        doc.examples.clone(),
+    )?;
+    set(
+        &mut message,
+        "@since",
+        &["since"],
+        doc.since.iter().cloned().collect(),
+    )?;
+    set(&mut message, "@see", &["see", "see_also"], doc.see.clone())?;
+    Ok(message)
+}
+
+fn set(
+    message: &mut DynamicMessage,
+    part: &'static str,
+    names: &[&'static str],
+    values: Vec<String>,
+) -> Result<(), FieldError> {
+    let values: Vec<String> = values
+        .into_iter()
+        .filter(|value| !value.is_empty())
+        .collect();
+    if values.is_empty() {
+        return Ok(());
+    }
+    let desc = message.descriptor();
+    let field = names
+        .iter()
+        .find_map(|name| desc.get_field_by_name(name))
+        .ok_or(FieldError::Missing(names[0]))?;
+    let value = match field.kind() {
+        Kind::String if field.is_list() => {
+            Value::List(values.into_iter().map(Value::String).collect())
+        }
+        Kind::String => Value::String(values.join("\n")),
+        Kind::Bool if !field.is_list() => Value::Bool(true),
+        _ => {
+            return Err(FieldError::NotText {
+                part,
+                field: field.name().to_string(),
+            })
+        }
+    };
+    message.set_field(&field, value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse() {
+        let comment = " Summary.\n\n A body\n @unknown tag\n on two lines.\n @deprecated\n @since 1.2\n @see Other\n @example\n     let a = 1;\n\n     let b = 2;\n";
+        assert_eq!(
+            parse(comment),
+            Doc {
+                summary: "Summary.".to_string(),
+                body: "A body @unknown tag on two lines.".to_string(),
+                deprecated: Some(String::new()),
+                examples: vec!["let a = 1;\n\nlet b = 2;".to_string()],
+                since: Some("1.2".to_string()),
+                see: vec!["Other".to_string()],
+            }
+        );
+    }
+    #[test]
+    fn test_to_message() {
+        use prost_reflect::prost_types::{
+            field_descriptor_proto::Type, DescriptorProto, FieldDescriptorProto,
+            FileDescriptorProto, FileDescriptorSet,
+        };
+        use prost_reflect::DescriptorPool;
+        let field = |name: &str, number: i32, type_: Type| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(type_ as i32),
+            ..Default::default()
+        };
+        let file = FileDescriptorProto {
+            name: Some("doc.proto".to_string()),
+            package: Some("doc".to_string()),
+            message_type: vec![DescriptorProto {
+                name: Some("Doc".to_string()),
+                field: vec![
+                    field("summary", 1, Type::String),
+                    field("since", 5, Type::Int32),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let pool = DescriptorPool::from_file_descriptor_set(FileDescriptorSet { file: vec![file] })
+            .unwrap();
+        let desc = pool.get_message_by_name("doc.Doc").unwrap();
+        let message = to_message(&parse(" Summary.\n"), &desc).unwrap();
+        assert_eq!(
+            message.get_field_by_name("summary").unwrap().as_str(),
+            Some("Summary.")
+        );
+        assert_eq!(
+            to_message(&parse(" Summary.\n @since 1.2\n"), &desc).unwrap_err(),
+            FieldError::NotText {
+                part: "@since",
+                field: "since".to_string()
+            }
+        );
+        assert_eq!(
+            to_message(&parse(" Summary.\n\n A body.\n"), &desc).unwrap_err(),
+            FieldError::Missing("body")
+        );
+    }
+    #[test]
+    fn test_parse_fence() {
+        let comment = " Summary.\n ```\n @see not a tag\n ```\n";
+        let doc = parse(comment);
+        assert_eq!(doc.body, "```\n@see not a tag\n```");
+        assert!(doc.see.is_empty());
+    }
+}
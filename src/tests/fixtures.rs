@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use protox::Compiler;
+
+use crate::comments2option::DescriptionIds;
+
+/// The directory of the test fixtures
+pub(crate) fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/fixtures")
+}
+
+/// A compiler with the fixture `name` opened, keeping the source info and the imports
+pub(crate) fn compile(name: &str) -> Compiler {
+    let mut compiler = Compiler::new([fixtures()]).unwrap();
+    compiler.include_imports(true);
+    compiler.include_source_info(true);
+    compiler.open_file(name).unwrap();
+    compiler
+}
+
+/// The description ids of the fixtures, 1000 for every kind of element
+pub(crate) fn test_ids() -> DescriptionIds {
+    let id = Some(1000);
+    DescriptionIds {
+        file: id,
+        message: id,
+        enum_: id,
+        service: id,
+        method: id,
+        field: id,
+        enum_value: id,
+        extension: id,
+        oneof: id,
+    }
+}
@@ -1 +1,2 @@
-pub(crate) mod compare_fds;
\ No newline at end of file
+pub(crate) mod compare_fds;
+pub(crate) mod fixtures;